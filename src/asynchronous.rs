@@ -0,0 +1,210 @@
+//! An async API on tokio, behind the `async` feature.
+//!
+//! Transitions are futures that write each step and sleep in between, so dropping one (or
+//! losing a `select!`) cancels it where it is. The daemon is a task driven by its sensors'
+//! sampling interval, kernel uevents and the [`Command`]s sent through its [`DaemonHandle`].
+//!
+//! Sysfs reads and writes are short and stay synchronous.
+
+use std::{future, io};
+
+use log::{info, warn};
+use tokio::{
+    io::{Interest, unix::AsyncFd},
+    sync::{mpsc, oneshot},
+    task::JoinHandle,
+    time::{self, Instant},
+};
+
+use crate::{
+    Device, Limits, Result, Value,
+    daemon::{Daemon, DaemonSetting, is_hotplug},
+    target_brightness,
+    transition::{Transition, TransitionSetting},
+    uevent::UeventSocket,
+};
+
+/// Write each step of `transition` to `device`. Dropping the future cancels the transition,
+/// leaving the brightness at the last step written.
+pub async fn run_transition(device: &mut Device, transition: Transition) -> Result<()> {
+    for (brightness, delay) in transition {
+        device.set_brightness(brightness)?;
+        time::sleep(delay).await;
+    }
+    Ok(())
+}
+
+/// Like [`crate::set_brightness`], without blocking the thread during the transition.
+pub async fn set_brightness(
+    device: &mut Device,
+    value: Value,
+    transition: &TransitionSetting,
+    limits: &Limits,
+) -> Result<()> {
+    let (current_brightness, new_brightness) = target_brightness(device, value, limits)?;
+    run_transition(
+        device,
+        Transition::new(current_brightness, new_brightness, transition),
+    )
+    .await
+}
+
+/// What a daemon started with [`spawn_daemon`] can be asked to do, like the commands of the
+/// [`Ipc`](crate::ipc::Ipc) socket.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Command {
+    /// Stop following the sensors, leaving the brightness alone.
+    Pause,
+    /// Follow the sensors again.
+    Resume,
+    /// Pause when running, resume when paused.
+    Toggle,
+    /// Apply a new setting, keeping the devices and sensors still in use.
+    Reload(DaemonSetting),
+    /// Reply with one line per device with the last illuminance and brightness.
+    Status(oneshot::Sender<String>),
+    /// Finish in-flight transitions (or restore the brightness, with `restore_on_exit`) and
+    /// return.
+    Stop,
+}
+
+/// A daemon running as a tokio task.
+pub struct DaemonHandle {
+    commands: mpsc::Sender<Command>,
+    task: JoinHandle<Result<()>>,
+}
+
+impl DaemonHandle {
+    /// Send `command`, returning it back if the daemon has already returned.
+    pub async fn send(&self, command: Command) -> std::result::Result<(), Command> {
+        self.commands.send(command).await.map_err(|e| e.0)
+    }
+    /// The daemon's status, `None` if it has already returned.
+    pub async fn status(&self) -> Option<String> {
+        let (sender, receiver) = oneshot::channel();
+        self.send(Command::Status(sender)).await.ok()?;
+        receiver.await.ok()
+    }
+    /// Ask the daemon to stop and wait for it.
+    pub async fn stop(self) -> Result<()> {
+        // an error means the daemon has already returned, with the result awaited below
+        let _ = self.commands.send(Command::Stop).await;
+        self.task.await.map_err(io::Error::other)?
+    }
+}
+
+/// Spawn [`run_daemon`] on the current tokio runtime.
+pub fn spawn_daemon(setting: DaemonSetting) -> DaemonHandle {
+    let (sender, receiver) = mpsc::channel(8);
+    DaemonHandle {
+        commands: sender,
+        task: tokio::spawn(run_daemon(setting, receiver)),
+    }
+}
+
+/// Wait for uevents announcing a device or sensor, forever without `uevents`.
+async fn hotplug(uevents: Option<&AsyncFd<UeventSocket>>) -> io::Result<()> {
+    let Some(uevents) = uevents else {
+        return future::pending().await;
+    };
+    loop {
+        let mut guard = uevents.readable().await?;
+        let received = guard.get_inner().recv();
+        // `recv` reads until the socket would block or fails
+        guard.clear_ready();
+        if is_hotplug(&received?) {
+            return Ok(());
+        }
+    }
+}
+
+/// The async counterpart of [`crate::daemon::run`]: sample the sensors every `interval` and
+/// fade every device towards its curve's output, until [`Command::Stop`] is received or every
+/// sender is dropped.
+///
+/// Unlike `lilight daemon`, this doesn't take the per-user [`Lock`](crate::lock::Lock), handle
+/// signals, watch the config file or listen on the control socket, which is left to the
+/// application embedding it.
+pub async fn run_daemon(
+    setting: DaemonSetting,
+    mut commands: mpsc::Receiver<Command>,
+) -> Result<()> {
+    let mut uevents = UeventSocket::new()
+        .and_then(|socket| {
+            // SAFETY: the socket owns its fd, which is only closed when the `AsyncFd` is dropped
+            unsafe { AsyncFd::register_with_interest(socket, Interest::READABLE) }
+                .map_err(|e| e.into_parts().1)
+        })
+        .inspect_err(|e| warn!("cannot listen for uevents, polling instead: {e}"))
+        .ok();
+    let mut daemon = Daemon::new(setting);
+    let mut next_sample = Instant::now();
+    loop {
+        let now = Instant::now();
+        if now >= next_sample {
+            if !daemon.paused {
+                daemon.sample(now.into_std());
+            }
+            next_sample = now + daemon.interval;
+        }
+        daemon.step(now.into_std());
+
+        let deadline = Instant::from_std(daemon.deadline(next_sample.into_std()));
+        tokio::select! {
+            () = time::sleep_until(deadline) => {}
+            result = hotplug(uevents.as_ref()) => match result {
+                // take a new sample right away with whatever appeared
+                Ok(()) => next_sample = Instant::now(),
+                Err(e) => {
+                    warn!("cannot receive uevents, polling instead: {e}");
+                    uevents = None;
+                }
+            },
+            command = commands.recv() => match command {
+                None | Some(Command::Stop) => {
+                    daemon.shutdown();
+                    return Ok(());
+                }
+                Some(Command::Status(reply)) => {
+                    // the caller may have given up waiting
+                    let _ = reply.send(daemon.status());
+                }
+                Some(Command::Reload(setting)) => {
+                    daemon.configure(setting);
+                    next_sample = Instant::now();
+                    info!("config reloaded");
+                }
+                Some(command) => {
+                    let paused = match command {
+                        Command::Pause => true,
+                        Command::Resume => false,
+                        _ => !daemon.paused,
+                    };
+                    if paused != daemon.paused {
+                        daemon.paused = paused;
+                        next_sample = Instant::now();
+                        info!("{}", if paused { "paused" } else { "resumed" });
+                    }
+                }
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Command, spawn_daemon};
+    use crate::daemon::DaemonSetting;
+
+    #[tokio::test]
+    async fn commands() {
+        let daemon = spawn_daemon(DaemonSetting::new(vec![]));
+        assert_eq!(daemon.status().await.as_deref(), Some(""));
+        daemon.send(Command::Pause).await.unwrap();
+        assert_eq!(daemon.status().await.as_deref(), Some("paused"));
+        daemon.send(Command::Toggle).await.unwrap();
+        assert_eq!(daemon.status().await.as_deref(), Some(""));
+        daemon.stop().await.unwrap();
+    }
+}
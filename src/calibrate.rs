@@ -0,0 +1,242 @@
+use std::{
+    fs,
+    io::{self, Read, Write},
+    path::Path,
+};
+
+use toml_edit::{Array, DocumentMut, InlineTable, Item, Table, value};
+
+use lilight::{Device, Error, Iio, Result, curve::Unit, to_value};
+
+/// Puts the terminal into non-canonical mode and restores it on drop.
+struct RawMode(libc::termios);
+
+impl RawMode {
+    fn enable() -> io::Result<Self> {
+        if unsafe { libc::isatty(libc::STDIN_FILENO) } != 1 {
+            return Err(io::Error::other("calibrate needs an interactive terminal"));
+        }
+        let mut termios = unsafe { std::mem::zeroed() };
+        if unsafe { libc::tcgetattr(libc::STDIN_FILENO, &mut termios) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let original = termios;
+        termios.c_lflag &= !(libc::ICANON | libc::ECHO | libc::ISIG);
+        // return from `read` after 0.5s without input, so the reading keeps updating
+        termios.c_cc[libc::VMIN] = 0;
+        termios.c_cc[libc::VTIME] = 5;
+        if unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &termios) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Self(original))
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &self.0) };
+    }
+}
+
+enum Key {
+    Up,
+    Down,
+    Record,
+    Finish,
+    Abort,
+}
+
+fn read_key() -> io::Result<Option<Key>> {
+    let mut buf = [0; 8];
+    let n = io::stdin().read(&mut buf)?;
+    Ok(match &buf[..n] {
+        b"\x1b[A" | b"\x1b[C" | b"+" | b"k" | b"l" => Some(Key::Up),
+        b"\x1b[B" | b"\x1b[D" | b"-" | b"j" | b"h" => Some(Key::Down),
+        b"\n" | b"\r" | b" " => Some(Key::Record),
+        b"q" => Some(Key::Finish),
+        b"\x1b" | b"\x03" => Some(Key::Abort),
+        _ => None,
+    })
+}
+
+/// `brightness` moved `direction` steps of 1%, or of one raw value on devices with fewer than
+/// 100, so every key press changes it.
+fn nudge(max_brightness: i32, brightness: i32, direction: i32) -> i32 {
+    let step = to_value(max_brightness, 1.0).max(1);
+    (brightness + step * direction).clamp(0, max_brightness)
+}
+
+/// Interactively record `(illuminance, brightness in unit)` points and write them as the curve
+/// of the config file at `config_path`, either the `[iio]` one or, with `curve_owner`, the one
+/// in `[device."<curve_owner>"]`.
+pub fn run(
+    device: &mut Device,
+    iio: &mut Iio,
+    unit: Unit,
+    config_path: &Path,
+    curve_owner: Option<&Path>,
+) -> Result<()> {
+    let max_brightness = device.get_max_brightness()?;
+    let mut points: Vec<(f64, f64)> = vec![];
+
+    println!("up/+/k: brighter, down/-/j: dimmer, enter/space: record point, q: save, esc: abort");
+    {
+        let _raw_mode = RawMode::enable()?;
+        loop {
+            let illuminance = iio.get_illuminance()?;
+            let brightness = device.get_brightness()?;
+            let percentage = brightness as f64 * 100.0 / max_brightness as f64;
+            print!(
+                "\r\x1b[2Killuminance: {illuminance:.2} lux, brightness: {percentage:.1}%, points: {}",
+                points.len()
+            );
+            io::stdout().flush()?;
+            match read_key()? {
+                Some(Key::Up) => device.set_brightness(nudge(max_brightness, brightness, 1))?,
+                Some(Key::Down) => device.set_brightness(nudge(max_brightness, brightness, -1))?,
+                Some(Key::Record) => {
+                    let illuminance = (illuminance * 1000.0).round() / 1000.0;
+                    let y = (unit.to_unit(max_brightness, brightness) * 10.0).round() / 10.0;
+                    points.retain(|(x, _)| *x != illuminance);
+                    points.push((illuminance, y));
+                }
+                Some(Key::Finish) => break,
+                Some(Key::Abort) => {
+                    println!();
+                    return Ok(());
+                }
+                None => {}
+            }
+        }
+    }
+    println!();
+
+    if points.is_empty() {
+        println!("no point recorded, config file is left unchanged");
+        return Ok(());
+    }
+    points.sort_by(|a, b| a.0.total_cmp(&b.0));
+    write_curve(config_path, curve_owner, &points)?;
+    println!("wrote {} points to `{config_path:?}`", points.len());
+    Ok(())
+}
+
+fn write_curve(
+    config_path: &Path,
+    curve_owner: Option<&Path>,
+    points: &[(f64, f64)],
+) -> Result<()> {
+    let config_error = |reason: &str| Error::Config {
+        path: config_path.to_path_buf(),
+        reason: reason.to_owned(),
+    };
+    let mut document = match fs::read_to_string(config_path) {
+        Ok(x) => x
+            .parse::<DocumentMut>()
+            .map_err(|e| config_error(&format!("parser error: {e}")))?,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => DocumentMut::new(),
+        Err(e) => return Err(Error::io(config_path, e)),
+    };
+
+    let curve = points
+        .iter()
+        .map(|&(x, y)| {
+            let mut point = InlineTable::new();
+            point.insert("x", x.into());
+            point.insert("y", y.into());
+            point
+        })
+        .collect::<Array>();
+    let parent = match curve_owner {
+        Some(device) => {
+            let devices = document
+                .entry("device")
+                .or_insert(Item::Table(Table::new()))
+                .as_table_like_mut()
+                .ok_or_else(|| config_error("`device` is not a table"))?;
+            devices
+                .entry(&device.to_string_lossy())
+                .or_insert(Item::Table(Table::new()))
+        }
+        None => document.entry("iio").or_insert(Item::Table(Table::new())),
+    }
+    .as_table_like_mut()
+    .ok_or_else(|| config_error("the curve's parent is not a table"))?;
+    match parent.get_mut("curve").and_then(Item::as_table_like_mut) {
+        Some(table) => {
+            table.insert("points", value(curve));
+        }
+        None => {
+            parent.insert("curve", value(curve));
+        }
+    }
+
+    if let Some(parent) = config_path.parent() {
+        fs::create_dir_all(parent).map_err(|e| Error::io(parent, e))?;
+    }
+    fs::write(config_path, document.to_string()).map_err(|e| Error::io(config_path, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, path::Path};
+
+    use super::{nudge, write_curve};
+    use crate::temp_dir::TempDir;
+    use lilight::{config::Config, curve::Scale};
+
+    #[test]
+    fn write_curve_keeps_other_keys() {
+        let dir = TempDir::new("calibrate");
+        let path = dir.join("lilight.toml");
+        fs::write(
+            &path,
+            "# my config\ndefault_device = \"backlight/x\"\n\n[iio]\ndefault_sensor = \"als\" # lid\ncurve = []\n",
+        )
+        .unwrap();
+        write_curve(&path, None, &[(0.5, 10.0), (100.0, 80.0)]).unwrap();
+        let written = fs::read_to_string(&path).unwrap();
+
+        assert!(written.contains("# my config"));
+        assert!(written.contains("default_sensor = \"als\" # lid"));
+        let config: Config = toml::from_str(&written).unwrap();
+        assert_eq!(config.iio.curve.apply(0.5), 10.0);
+        assert_eq!(config.iio.curve.apply(100.0), 80.0);
+    }
+
+    #[test]
+    fn write_curve_keeps_curve_settings() {
+        let dir = TempDir::new("calibrate-table");
+        let path = dir.join("lilight.toml");
+        fs::write(&path, "[iio.curve]\nx_scale = \"log\"\npoints = []\n").unwrap();
+        write_curve(&path, None, &[(1.0, 10.0)]).unwrap();
+        let written = fs::read_to_string(&path).unwrap();
+
+        let config: Config = toml::from_str(&written).unwrap();
+        assert!(matches!(config.iio.curve.x_scale, Scale::Log));
+        assert_eq!(config.iio.curve.points.len(), 1);
+    }
+
+    #[test]
+    fn write_device_curve() {
+        let dir = TempDir::new("calibrate-device");
+        let path = dir.join("lilight.toml");
+        fs::write(&path, "[device.\"leds/kbd\"]\nmin = \"5%\"\ncurve = []\n").unwrap();
+        write_curve(&path, Some(Path::new("leds/kbd")), &[(1.0, 10.0)]).unwrap();
+        let written = fs::read_to_string(&path).unwrap();
+
+        let config: Config = toml::from_str(&written).unwrap();
+        let device = &config.device[Path::new("leds/kbd")];
+        assert!(device.min.is_some());
+        assert_eq!(device.curve.as_ref().unwrap().points.len(), 1);
+    }
+
+    #[test]
+    fn small_steps() {
+        assert_eq!(nudge(10, 5, 1), 6);
+        assert_eq!(nudge(10, 0, 1), 1);
+        assert_eq!(nudge(10, 0, -1), 0);
+        assert_eq!(nudge(10, 10, 1), 10);
+        assert_eq!(nudge(1000, 500, -1), 490);
+    }
+}
@@ -1,8 +1,12 @@
-use std::{path::PathBuf, str::FromStr};
+use std::path::PathBuf;
 
-use clap::{Parser, Subcommand};
+use clap::{ArgAction, Parser, Subcommand, ValueEnum};
 
-#[derive(Debug, Parser)]
+use lilight::{Value, udev};
+
+use crate::compat::Tool;
+
+#[derive(Clone, Debug, Parser)]
 #[command(version, about)]
 pub struct Cli {
     #[command(subcommand)]
@@ -10,11 +14,19 @@ pub struct Cli {
 
     #[arg(short, long, global = true)]
     pub config: Option<PathBuf>,
+    /// Show more log messages, can be repeated
+    #[arg(short, long, global = true, action = ArgAction::Count)]
+    pub verbose: u8,
+    /// Show fewer log messages, can be repeated
+    #[arg(short, long, global = true, action = ArgAction::Count)]
+    pub quiet: u8,
 }
 
-#[derive(Debug, Subcommand)]
+#[derive(Clone, Debug, Subcommand)]
 pub enum Command {
     Set {
+        /// `50%` or `100` to set, `+10%`, `-2.5%`, `*1.5` or `/2` to change, `=` to force
+        /// absolute, or one of `max`, `min`, `half` and `off`
         #[arg(allow_hyphen_values = true)]
         value: Value,
 
@@ -28,63 +40,134 @@ pub enum Command {
     Get {
         #[arg(short, long)]
         max: bool,
+        /// Read `actual_brightness`, the brightness reported by the hardware
+        #[arg(long, conflicts_with = "max")]
+        actual: bool,
+        /// Print only the number, for scripts
+        #[arg(short, long)]
+        format: Option<Format>,
         #[arg(short, long, group = "devices")]
         device: Option<PathBuf>,
         #[arg(short, long, group = "devices")]
         all: bool,
     },
     List,
-    Daemon {
+    /// Power the backlight on or off through `bl_power`, keeping its brightness
+    Power {
+        state: PowerState,
         #[arg(short, long)]
         device: Option<PathBuf>,
+    },
+    /// List and read ambient light sensors
+    Sensor {
+        #[command(subcommand)]
+        command: SensorCommand,
+    },
+    /// Interactively record brightness for the current illuminance and write the curve
+    Calibrate {
+        #[arg(short, long)]
+        device: Option<PathBuf>,
+        /// `iio:deviceN`, the sensor's `name` or `label`, or the path of the underlying device
+        #[arg(short, long)]
+        iio: Option<String>,
+    },
+    /// Print files for integrating with other tools
+    Generate {
+        #[command(subcommand)]
+        command: GenerateCommand,
+    },
+    /// Print a udev rule letting a group change the brightness, or install it
+    UdevRule {
+        /// Group given write access to `brightness`
+        #[arg(short, long, default_value = "video")]
+        group: String,
+        /// Write the rule and reload udev instead of printing it, needs root
+        #[arg(short, long)]
+        install: bool,
+        /// Where `--install` writes the rule
+        #[arg(long, default_value = udev::DEFAULT_DESTINATION)]
+        destination: PathBuf,
+    },
+    /// Report the config, devices, sensors and daemon, and explain what's missing
+    Doctor {
+        /// Print the report as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Take the arguments of brightnessctl or light, like when lilight is started through a
+    /// symlink named after either
+    Compat {
+        tool: Tool,
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        args: Vec<String>,
+    },
+    Daemon {
+        /// Can be repeated to drive several devices at once
+        #[arg(short, long)]
+        device: Vec<PathBuf>,
         #[arg(short, long)]
         transition_time: Option<u64>,
         #[arg(short = 's', long)]
         transition_step: Option<u64>,
+        /// `iio:deviceN`, the sensor's `name` or `label`, or the path of the underlying device.
+        /// Can be repeated to combine several sensors
         #[arg(short, long)]
-        iio: Option<PathBuf>,
+        iio: Vec<String>,
+        /// Milliseconds between sensor readings
+        #[arg(short = 'n', long)]
+        interval: Option<u64>,
+        /// Restore the brightness from before the daemon started when it exits
+        #[arg(short, long)]
+        restore: bool,
+        /// Ask an already running daemon to exit and take over from it
+        #[arg(long)]
+        replace: bool,
     },
 }
 
-#[derive(Clone, Debug)]
-pub struct Value {
-    pub prefix: Prefix,
-    pub r#type: Type,
-    pub num: i32,
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum Format {
+    /// The raw value, like `brightnessctl get`
+    Raw,
+    /// Percent of `max_brightness` with two decimals, like `light -G`
+    Percent,
 }
 
-impl FromStr for Value {
-    type Err = String;
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let (prefix, s) = match s.chars().next() {
-            Some('+') => (Prefix::Plus, &s[1..]),
-            Some('-') => (Prefix::Minus, &s[1..]),
-            Some(_) => (Prefix::None, s),
-            None => Err("the value is empty")?,
-        };
-        let (r#type, s) = if s.ends_with("%") {
-            (Type::Percentage, &s[..s.len() - 1])
-        } else {
-            (Type::Number, s)
-        };
-        let num = s.parse().map_err(|e| format!("parsing error: {e}"))?;
-        Ok(Self {
-            prefix,
-            r#type,
-            num,
-        })
+impl Format {
+    pub fn format(self, value: i32, max: i32) -> String {
+        match self {
+            Self::Raw => value.to_string(),
+            Self::Percent => format!("{:.2}", value as f64 * 100.0 / max as f64),
+        }
     }
 }
 
-#[derive(Clone, Debug)]
-pub enum Prefix {
-    None,
-    Plus,
-    Minus,
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum PowerState {
+    On,
+    Off,
+    Toggle,
 }
 
-#[derive(Clone, Debug)]
-pub enum Type {
-    Number,
-    Percentage,
+#[derive(Clone, Debug, Subcommand)]
+pub enum GenerateCommand {
+    /// A systemd user service running `lilight daemon` with `Type=notify`
+    SystemdUnit {
+        /// Print the socket unit for activating the daemon through its control socket instead
+        #[arg(short, long)]
+        socket: bool,
+    },
+}
+
+#[derive(Clone, Debug, Subcommand)]
+pub enum SensorCommand {
+    List,
+    Read {
+        /// `iio:deviceN`, the sensor's `name` or `label`, or the path of the underlying device
+        #[arg(short, long)]
+        sensor: Option<String>,
+        /// Keep printing a reading every second
+        #[arg(short, long)]
+        follow: bool,
+    },
 }
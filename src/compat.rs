@@ -0,0 +1,309 @@
+use std::{ffi::OsStr, path::PathBuf};
+
+use clap::ValueEnum;
+use lilight::Value;
+use log::warn;
+
+use crate::cli::{Cli, Command, Format};
+
+/// Command line syntaxes lilight understands besides its own, through `lilight compat <tool>`
+/// or a symlink named after the tool.
+#[derive(Clone, Copy, Debug, ValueEnum)]
+pub enum Tool {
+    /// `brightnessctl set 5%+`, `brightnessctl -d intel_backlight get`, ...
+    Brightnessctl,
+    /// `light -A 5`, `light -s sysfs/backlight/intel_backlight -S 50`, ...
+    Light,
+}
+
+impl Tool {
+    /// The tool `program` (`argv[0]`) is named after, if lilight was started through a symlink.
+    pub fn from_program(program: &OsStr) -> Option<Self> {
+        match std::path::Path::new(program).file_name()?.to_str()? {
+            "brightnessctl" => Some(Self::Brightnessctl),
+            "light" => Some(Self::Light),
+            _ => None,
+        }
+    }
+
+    /// Translate the arguments `tool` would be given, without the program name, into the
+    /// command of `cli`, which keeps lilight's own global flags.
+    pub fn translate(self, args: &[String], mut cli: Cli) -> Result<Cli, String> {
+        cli.command = match self {
+            Self::Brightnessctl => brightnessctl(args, &mut cli)?,
+            Self::Light => light(args)?,
+        };
+        Ok(cli)
+    }
+}
+
+fn parse_value(s: &str) -> Result<Value, String> {
+    s.parse::<Value>().map_err(|e| e.to_string())
+}
+
+/// brightnessctl names devices without their class, which is only given with `--class`.
+fn brightnessctl_device(name: String, class: Option<String>) -> PathBuf {
+    let class = class.unwrap_or_else(|| {
+        match ["backlight", "leds"]
+            .into_iter()
+            .find(|class| PathBuf::from("/sys/class").join(class).join(&name).exists())
+        {
+            Some(class) => class.to_owned(),
+            None => "backlight".to_owned(),
+        }
+    });
+    PathBuf::from(class).join(name)
+}
+
+fn brightnessctl(args: &[String], cli: &mut Cli) -> Result<Command, String> {
+    let mut device = None;
+    let mut class = None;
+    let mut list = false;
+    let mut positional = vec![];
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let (option, inline) = match arg.split_once('=') {
+            Some((option, value)) if arg.starts_with("--") => (option, Some(value.to_owned())),
+            _ => (arg.as_str(), None),
+        };
+        let mut value = |name: &str| {
+            inline
+                .clone()
+                .or_else(|| args.next().cloned())
+                .ok_or(format!("`{name}` needs a value"))
+        };
+        match option {
+            "-l" | "--list" => list = true,
+            "-q" | "--quiet" => cli.quiet += 1,
+            "-m" | "--machine-readable" => {}
+            "-d" | "--device" => device = Some(value(option)?),
+            "-c" | "--class" => class = Some(value(option)?),
+            _ if option.starts_with("-n") || option == "--min-value" => {
+                warn!(
+                    "`{option}` is ignored, set `min` in the device's table of the config instead"
+                );
+            }
+            _ if option.starts_with('-') && option.len() > 1 && parse_value(option).is_err() => {
+                return Err(format!("brightnessctl's `{option}` is not supported"));
+            }
+            _ => positional.push(arg.as_str()),
+        }
+    }
+    let device = device.map(|name| brightnessctl_device(name, class));
+    if list {
+        return Ok(Command::List);
+    }
+    match positional[..] {
+        [] | ["i" | "info"] => Ok(Command::Get {
+            max: false,
+            actual: false,
+            format: None,
+            device,
+            all: false,
+        }),
+        ["g" | "get"] => Ok(Command::Get {
+            max: false,
+            actual: false,
+            format: Some(Format::Raw),
+            device,
+            all: false,
+        }),
+        ["m" | "max"] => Ok(Command::Get {
+            max: true,
+            actual: false,
+            format: Some(Format::Raw),
+            device,
+            all: false,
+        }),
+        ["s" | "set", value] => {
+            // brightnessctl puts the sign of a change after the value, `5%+`
+            let value = match (value.strip_suffix('+'), value.strip_suffix('-')) {
+                (Some(rest), _) => format!("+{rest}"),
+                (_, Some(rest)) => format!("-{rest}"),
+                _ => value.to_owned(),
+            };
+            Ok(Command::Set {
+                value: parse_value(&value)?,
+                device,
+                transition_time: None,
+                transition_step: None,
+            })
+        }
+        ["s" | "set"] => Err("`set` needs a value".to_owned()),
+        _ => Err(format!(
+            "unsupported brightnessctl operation `{}`",
+            positional.join(" ")
+        )),
+    }
+}
+
+fn light(args: &[String]) -> Result<Command, String> {
+    // light takes percentages unless `-r` is given anywhere, and `-T` a factor
+    let raw = args.iter().any(|arg| arg == "-r");
+    let mut device = None;
+    let mut operation = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("`{arg}` needs a value"));
+        match arg.as_str() {
+            "-r" => {}
+            "-v" => {
+                value()?;
+            }
+            "-s" => {
+                let path = value()?;
+                // light prefixes devices with their controller, only sysfs is supported
+                let path = path
+                    .strip_prefix("sysfs/")
+                    .ok_or(format!("unsupported light device `{path}`"))?;
+                device = Some(PathBuf::from(path));
+            }
+            "-G" | "-M" | "-L" if operation.is_none() => operation = Some((arg.as_str(), None)),
+            "-S" | "-A" | "-U" | "-T" if operation.is_none() => {
+                operation = Some((arg.as_str(), Some(value()?)));
+            }
+            "-G" | "-M" | "-L" | "-S" | "-A" | "-U" | "-T" => {
+                return Err("light takes a single operation".to_owned());
+            }
+            _ => return Err(format!("light's `{arg}` is not supported")),
+        }
+    }
+    // light prints percentages with two decimals, or raw values with `-r`
+    let format = Some(if raw { Format::Raw } else { Format::Percent });
+    let set = |prefix: &str, value: &str| {
+        let suffix = if raw || prefix == "*" { "" } else { "%" };
+        Ok(Command::Set {
+            value: parse_value(&format!("{prefix}{value}{suffix}"))?,
+            device: device.clone(),
+            transition_time: None,
+            transition_step: None,
+        })
+    };
+    match operation {
+        None | Some(("-G", _)) => Ok(Command::Get {
+            max: false,
+            actual: false,
+            format,
+            device,
+            all: false,
+        }),
+        Some(("-M", _)) => Ok(Command::Get {
+            max: true,
+            actual: false,
+            format,
+            device,
+            all: false,
+        }),
+        Some(("-L", _)) => Ok(Command::List),
+        Some(("-A", Some(value))) => set("+", value),
+        Some(("-U", Some(value))) => set("-", value),
+        Some(("-T", Some(value))) => set("*", value),
+        Some((_, value)) => set("=", value.expect("every other operation takes a value")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use lilight::{Prefix, Type};
+
+    use super::Tool;
+    use crate::cli::{Cli, Command, Format};
+
+    fn cli() -> Cli {
+        Cli {
+            command: Command::List,
+            config: Some("lilight.toml".into()),
+            verbose: 1,
+            quiet: 0,
+        }
+    }
+
+    fn translate(tool: Tool, args: &str) -> Command {
+        let args = args.split(' ').map(str::to_owned).collect::<Vec<_>>();
+        tool.translate(&args, cli()).unwrap().command
+    }
+
+    #[test]
+    fn brightnessctl() {
+        let Command::Set { value, device, .. } = translate(
+            Tool::Brightnessctl,
+            "-c leds --device=kbd_backlight set 5%-",
+        ) else {
+            panic!()
+        };
+        assert!(matches!(value.prefix, Prefix::Minus));
+        assert!(matches!(value.r#type, Type::Percentage));
+        assert_eq!(value.num, 5.0);
+        assert_eq!(device.as_deref(), Some(Path::new("leds/kbd_backlight")));
+        assert!(matches!(
+            translate(Tool::Brightnessctl, "max"),
+            Command::Get {
+                max: true,
+                format: Some(Format::Raw),
+                ..
+            }
+        ));
+        assert!(matches!(
+            translate(Tool::Brightnessctl, "-l"),
+            Command::List
+        ));
+        assert!(
+            Tool::Brightnessctl
+                .translate(&["-s".to_owned()], cli())
+                .is_err()
+        );
+        let translated = Tool::Brightnessctl
+            .translate(&["-q".to_owned(), "get".to_owned()], cli())
+            .unwrap();
+        assert_eq!(
+            translated.config.as_deref(),
+            Some(Path::new("lilight.toml"))
+        );
+        assert_eq!((translated.verbose, translated.quiet), (1, 1));
+    }
+
+    #[test]
+    fn light() {
+        let Command::Set { value, device, .. } =
+            translate(Tool::Light, "-s sysfs/backlight/acpi_video0 -A 5")
+        else {
+            panic!()
+        };
+        assert!(matches!(value.prefix, Prefix::Plus));
+        assert!(matches!(value.r#type, Type::Percentage));
+        assert_eq!(device.as_deref(), Some(Path::new("backlight/acpi_video0")));
+        let Command::Set { value, .. } = translate(Tool::Light, "-r -S 100") else {
+            panic!()
+        };
+        assert!(matches!(value.prefix, Prefix::None));
+        assert!(matches!(value.r#type, Type::Number));
+        assert!(matches!(
+            translate(Tool::Light, "-G"),
+            Command::Get {
+                format: Some(Format::Percent),
+                ..
+            }
+        ));
+        assert!(matches!(
+            translate(Tool::Light, "-r -G"),
+            Command::Get {
+                format: Some(Format::Raw),
+                ..
+            }
+        ));
+        assert!(
+            Tool::Light
+                .translate(&["-G".to_owned(), "-L".to_owned()], cli())
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn format() {
+        assert_eq!(Format::Raw.format(469, 937), "469");
+        assert_eq!(Format::Percent.format(469, 937), "50.05");
+        assert_eq!(Format::Percent.format(937, 937), "100.00");
+    }
+}
@@ -1,31 +1,51 @@
-use std::path::PathBuf;
+//! The config file, deserialized with serde.
 
-use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
 
-use crate::curve::{Curve, CurvePoint};
+use log::LevelFilter;
+use serde::{Deserialize, Deserializer, de::Error};
 
-#[derive(Deserialize)]
+use crate::{
+    curve::{Curve, CurvePoint},
+    error,
+    fusion::Fusion,
+    value::{Prefix, Value},
+};
+
+/// The config file, `~/.config/lilight/lilight.toml` by default.
+#[derive(Default, Deserialize)]
 #[serde(default)]
+#[non_exhaustive]
 pub struct Config {
+    /// Device used when none is given, relative to `/sys/class` (e.g. `backlight/acpi_video0`).
     pub default_device: Option<PathBuf>,
+    /// `off`, `error`, `warn`, `info`, `debug` or `trace`, overridden by `$LILIGHT_LOG`.
+    #[serde(deserialize_with = "deserialize_log_level")]
+    pub log_level: Option<LevelFilter>,
+    /// The `[transition]` table.
     pub transition: Transition,
+    /// The `[iio]` table.
     pub iio: Iio,
+    /// Per-device overrides, keyed like `default_device` (e.g. `[device."leds/kbd_backlight"]`).
+    pub device: HashMap<PathBuf, Device>,
+    /// The `[daemon]` table.
+    pub daemon: Daemon,
 }
 
-impl Default for Config {
-    fn default() -> Self {
-        Self {
-            default_device: None,
-            transition: Transition::default(),
-            iio: Iio::default(),
-        }
-    }
-}
-
+/// How brightness changes fade.
 #[derive(Deserialize)]
+#[serde(default)]
+#[non_exhaustive]
 pub struct Transition {
+    /// Fade instead of jumping to the new brightness.
     pub enable: bool,
+    /// Milliseconds a fade takes.
     pub time: u64,
+    /// Milliseconds between two writes during a fade.
     pub step: u64,
 }
 
@@ -39,10 +59,20 @@ impl Default for Transition {
     }
 }
 
+/// Ambient light sensor settings.
 #[derive(Deserialize)]
+#[serde(default)]
+#[non_exhaustive]
 pub struct Iio {
-    pub default_sensor: Option<PathBuf>,
-    // TODO: maybe support for different unit (e.g. value, percentage ...)
+    /// `iio:deviceN`, the sensor's `name` or `label`, or the path of the underlying device.
+    pub default_sensor: Option<String>,
+    /// Several sensors to combine with `fusion`, used by the daemon instead of `default_sensor`.
+    pub sensors: Vec<String>,
+    /// How readings from `sensors` are combined.
+    pub fusion: Fusion,
+    /// Weights for `fusion = "weighted"`, in the same order as `sensors`.
+    pub weights: Vec<f64>,
+    /// Maps illuminance to brightness, from 0% at 0 lux to 100% at 100 lux by default.
     pub curve: Curve,
 }
 
@@ -50,10 +80,172 @@ impl Default for Iio {
     fn default() -> Self {
         Self {
             default_sensor: None,
+            sensors: vec![],
+            fusion: Fusion::default(),
+            weights: vec![],
             curve: Curve::new(vec![
-                CurvePoint { x: 0, y: 0 },
-                CurvePoint { x: 100, y: 100 },
+                CurvePoint { x: 0.0, y: 0.0 },
+                CurvePoint { x: 100.0, y: 100.0 },
             ]),
         }
     }
 }
+
+impl Config {
+    /// Read and validate the config file at `config_path`.
+    pub fn read(config_path: &Path) -> error::Result<Self> {
+        let x = fs::read(config_path).map_err(|e| error::Error::io(config_path, e))?;
+        let config = toml::from_slice::<Self>(&x).map_err(|e| error::Error::Config {
+            path: config_path.to_path_buf(),
+            reason: format!("parser error: {e}"),
+        })?;
+        config.validate().map_err(|reason| error::Error::Config {
+            path: config_path.to_path_buf(),
+            reason,
+        })?;
+        Ok(config)
+    }
+    /// Catch values that parse but can't be used, so a running daemon can keep its current
+    /// config instead.
+    pub fn validate(&self) -> Result<(), String> {
+        self.iio
+            .curve
+            .validate()
+            .map_err(|e| format!("`iio.curve`: {e}"))?;
+        if self.iio.weights.iter().any(|x| *x < 0.0) {
+            Err("`iio.weights` must not be negative")?;
+        }
+        if self.daemon.interval == 0 {
+            Err("`daemon.interval` must be greater than 0")?;
+        }
+        for (name, device) in &self.device {
+            if let Some(curve) = &device.curve {
+                curve
+                    .validate()
+                    .map_err(|e| format!("`device.{name:?}.curve`: {e}"))?;
+            }
+            if device.weights.iter().any(|x| *x < 0.0) {
+                Err(format!("`device.{name:?}.weights` must not be negative"))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Settings only used by `lilight daemon`.
+#[derive(Deserialize)]
+#[serde(default)]
+#[non_exhaustive]
+pub struct Daemon {
+    /// Devices driven by the daemon, falling back to `default_device` when empty.
+    pub devices: Vec<PathBuf>,
+    /// Milliseconds between sensor readings.
+    pub interval: u64,
+    /// Set the devices back to their brightness from before the daemon started when it exits.
+    pub restore_on_exit: bool,
+}
+
+impl Default for Daemon {
+    fn default() -> Self {
+        Self {
+            devices: vec![],
+            interval: 1000,
+            restore_on_exit: false,
+        }
+    }
+}
+
+/// Overrides for a single device. Unset fields fall back to the global settings, and command
+/// line flags take precedence over both.
+#[derive(Clone, Default, Deserialize)]
+#[serde(default)]
+#[non_exhaustive]
+pub struct Device {
+    /// Replaces `iio.curve`.
+    pub curve: Option<Curve>,
+    /// Replaces fields of `[transition]`.
+    pub transition: DeviceTransition,
+    /// Lowest value lilight will set, e.g. `5%` or `10`.
+    #[serde(deserialize_with = "deserialize_limit")]
+    pub min: Option<Value>,
+    /// Highest value lilight will set, e.g. `80%` or `200`.
+    #[serde(deserialize_with = "deserialize_limit")]
+    pub max: Option<Value>,
+    /// Replaces `iio.default_sensor`.
+    pub sensor: Option<String>,
+    /// Replaces `iio.sensors`.
+    pub sensors: Vec<String>,
+    /// Replaces `iio.fusion`.
+    pub fusion: Option<Fusion>,
+    /// Replaces `iio.weights`.
+    pub weights: Vec<f64>,
+}
+
+/// Per-device [`Transition`], unset fields keeping the global value.
+#[derive(Clone, Default, Deserialize)]
+#[serde(default)]
+#[non_exhaustive]
+pub struct DeviceTransition {
+    /// Replaces `transition.enable`.
+    pub enable: Option<bool>,
+    /// Replaces `transition.time`.
+    pub time: Option<u64>,
+    /// Replaces `transition.step`.
+    pub step: Option<u64>,
+}
+
+fn deserialize_limit<'de, D>(deserializer: D) -> Result<Option<Value>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Limit {
+        Number(i32),
+        String(String),
+    }
+    let value = match Limit::deserialize(deserializer)? {
+        Limit::Number(num) => return Ok(Some(Value::number(num))),
+        Limit::String(s) => s.parse::<Value>().map_err(D::Error::custom)?,
+    };
+    match value.prefix {
+        Prefix::None => Ok(Some(value)),
+        _ => Err(D::Error::custom(
+            "a limit must be an absolute value such as `5%` or `10`",
+        )),
+    }
+}
+
+fn deserialize_log_level<'de, D>(deserializer: D) -> Result<Option<LevelFilter>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    String::deserialize(deserializer)?
+        .parse()
+        .map(Some)
+        .map_err(D::Error::custom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Config;
+
+    #[test]
+    fn example() {
+        let config = toml::from_str::<Config>(include_str!("../lilight.toml")).unwrap();
+        config.validate().unwrap();
+        assert_eq!(config.device.len(), 2);
+        assert_eq!(config.iio.curve.points.len(), 4);
+    }
+
+    #[test]
+    fn partial_tables() {
+        let config =
+            toml::from_str::<Config>("[transition]\ntime = 300\n[iio]\ndefault_sensor = \"als\"")
+                .unwrap();
+        config.validate().unwrap();
+        assert_eq!((config.transition.time, config.transition.step), (300, 17));
+        assert_eq!(config.iio.default_sensor.as_deref(), Some("als"));
+        assert_eq!(config.iio.curve.points.len(), 2);
+    }
+}
@@ -1,54 +1,308 @@
+//! Mapping from illuminance to brightness.
+
 use serde::Deserialize;
 
-#[derive(Debug, Deserialize)]
-pub struct Curve(Vec<CurvePoint>);
+/// Accepts either a bare list of points or a table with `points` and the optional settings.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(from = "CurveConfig")]
+#[non_exhaustive]
+pub struct Curve {
+    /// Interpolated linearly between neighbours, in any order.
+    pub points: Vec<CurvePoint>,
+    /// Space the points' `x` are interpolated in.
+    pub x_scale: Scale,
+    /// Unit of the points' `y`.
+    pub unit: Unit,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum CurveConfig {
+    Points(Vec<CurvePoint>),
+    Table {
+        points: Vec<CurvePoint>,
+        #[serde(default)]
+        x_scale: Scale,
+        #[serde(default)]
+        unit: Unit,
+    },
+}
+
+impl From<CurveConfig> for Curve {
+    fn from(value: CurveConfig) -> Self {
+        match value {
+            CurveConfig::Points(points) => Self::new(points),
+            CurveConfig::Table {
+                points,
+                x_scale,
+                unit,
+            } => Self {
+                points,
+                x_scale,
+                unit,
+            },
+        }
+    }
+}
 
 impl Curve {
+    /// A curve through `points`, in percent over linear lux.
     pub fn new(points: Vec<CurvePoint>) -> Self {
-        Self(points)
+        Self {
+            points,
+            x_scale: Scale::default(),
+            unit: Unit::default(),
+        }
+    }
+    /// Check there is at least one point and no two share the same `x`.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.points.is_empty() {
+            Err("the curve has no points")?;
+        }
+        for (i, point) in self.points.iter().enumerate() {
+            if !point.x.is_finite() || !point.y.is_finite() {
+                Err(format!("point {i} is not a finite number"))?;
+            }
+            if self.points[..i]
+                .iter()
+                .any(|x| self.x_scale.apply(x.x) == self.x_scale.apply(point.x))
+            {
+                Err(format!("more than one point at x = {}", point.x))?;
+            }
+        }
+        Ok(())
     }
-    pub fn apply(&self, point: i32) -> i32 {
-        let points = self.0.as_slice();
-        points
+    /// The curve's `y` at illuminance `point`, in [`Curve::unit`]: linear between the two
+    /// closest points, and the `y` of the first or last point outside them.
+    pub fn apply(&self, point: f64) -> f64 {
+        let x = self.x_scale.apply(point);
+        let mut points = self
+            .points
             .iter()
-            .enumerate()
-            .map(|(i, point_i)| {
-                points
-                    .iter()
-                    .enumerate()
-                    .fold(point_i.y, |pre, (j, point_j)| {
-                        if j == i {
-                            pre
-                        } else {
-                            pre * (point - point_j.x) / (point_i.x - point_j.x)
-                        }
-                    })
-            })
-            .sum()
-    }
-}
-
-#[derive(Debug, Deserialize)]
+            .map(|point| (self.x_scale.apply(point.x), point.y))
+            .collect::<Vec<_>>();
+        points.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let (Some(&(x_first, y_first)), Some(&(_, y_last))) = (points.first(), points.last())
+        else {
+            return 0.0;
+        };
+        if x <= x_first {
+            return y_first;
+        }
+        for pair in points.windows(2) {
+            let [(x_0, y_0), (x_1, y_1)] = [pair[0], pair[1]];
+            if x <= x_1 {
+                return y_0 + (y_1 - y_0) * (x - x_0) / (x_1 - x_0);
+            }
+        }
+        y_last
+    }
+}
+
+/// Space a [`Curve`] is interpolated in.
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[non_exhaustive]
+pub enum Scale {
+    /// Interpolate lux as is.
+    #[default]
+    Linear,
+    /// Interpolate in `log10(lux + 1)` space, so points a decade apart are evenly spread.
+    Log,
+}
+
+impl Scale {
+    fn apply(self, x: f64) -> f64 {
+        match self {
+            Self::Linear => x,
+            Self::Log => (x.max(0.0) + 1.0).log10(),
+        }
+    }
+}
+
+/// A point of a [`Curve`].
+#[derive(Clone, Debug, Deserialize)]
+#[non_exhaustive]
 pub struct CurvePoint {
-    pub x: i32,
-    pub y: i32,
+    /// Illuminance in lux.
+    pub x: f64,
+    /// Brightness, in the curve's [`Unit`].
+    pub y: f64,
+}
+
+impl CurvePoint {
+    /// `y` at `x` lux.
+    pub fn new(x: f64, y: f64) -> Self {
+        Self { x, y }
+    }
+}
+
+/// Unit of the curve's output (`y`).
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[non_exhaustive]
+pub enum Unit {
+    /// Raw value written to the device's `brightness`.
+    Raw,
+    /// Percentage of `max_brightness`.
+    #[default]
+    Percent,
+    /// Perceived lightness in percent (CIE 1976 L*), converted to linear before writing.
+    Perceptual,
+}
+
+impl Unit {
+    /// Raw brightness for `y` on a device whose `max_brightness` is `max`.
+    pub fn to_value(self, max: i32, y: f64) -> i32 {
+        match self {
+            Self::Raw => y.round() as i32,
+            Self::Percent => crate::value::to_value(max, y),
+            Self::Perceptual => {
+                let luminance = if y > 8.0 {
+                    ((y + 16.0) / 116.0).powi(3)
+                } else {
+                    y / 903.3
+                };
+                (luminance * max as f64).round() as i32
+            }
+        }
+    }
+    /// Inverse of [`Unit::to_value`].
+    pub fn to_unit(self, max: i32, value: i32) -> f64 {
+        match self {
+            Self::Raw => value as f64,
+            Self::Percent => value as f64 * 100.0 / max as f64,
+            Self::Perceptual => {
+                let luminance = value as f64 / max as f64;
+                if luminance > 0.008856 {
+                    116.0 * luminance.cbrt() - 16.0
+                } else {
+                    luminance * 903.3
+                }
+            }
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{Curve, CurvePoint};
+    use super::{Curve, CurvePoint, Scale, Unit};
 
     #[test]
     fn t() {
         let curve = Curve::new(vec![
-            CurvePoint { x: 0, y: 0 },
-            CurvePoint { x: 100, y: 50 },
+            CurvePoint { x: 0.0, y: 0.0 },
+            CurvePoint { x: 100.0, y: 50.0 },
         ]);
         for i in 0..=200 {
-            println!("{i}: {}", curve.apply(i));
+            println!("{i}: {}", curve.apply(i as f64));
+        }
+        assert_eq!(curve.apply(0.0), 0.0);
+        assert_eq!(curve.apply(100.0), 50.0);
+        assert_eq!(curve.apply(50.0), 25.0);
+    }
+
+    #[test]
+    fn calibrated() {
+        let curve = Curve::new(
+            [
+                (0.0, 5.0),
+                (20.0, 20.0),
+                (150.0, 45.0),
+                (400.0, 60.0),
+                (1000.0, 80.0),
+                (5000.0, 100.0),
+            ]
+            .into_iter()
+            .map(|(x, y)| CurvePoint { x, y })
+            .collect(),
+        );
+        let mut last = curve.apply(0.0);
+        for i in 0..=20000 {
+            let y = curve.apply(i as f64);
+            assert!(y >= last && (5.0..=100.0).contains(&y), "{i} lux: {y}");
+            last = y;
+        }
+        assert_eq!(curve.apply(250.0), 51.0);
+        assert_eq!(curve.apply(100000.0), 100.0);
+    }
+
+    #[test]
+    fn below_one_lux() {
+        let curve = Curve::new(vec![
+            CurvePoint { x: 0.0, y: 10.0 },
+            CurvePoint { x: 1.0, y: 20.0 },
+        ]);
+        assert_eq!(curve.apply(0.5), 15.0);
+        assert!(curve.apply(0.01) > 10.0);
+    }
+
+    #[test]
+    fn log_scale() {
+        let mut curve = Curve::new(vec![
+            CurvePoint { x: 9.0, y: 20.0 },
+            CurvePoint { x: 999.0, y: 80.0 },
+        ]);
+        curve.x_scale = Scale::Log;
+        assert!((curve.apply(9.0) - 20.0).abs() < 1e-9);
+        assert!((curve.apply(99.0) - 50.0).abs() < 1e-9);
+        assert!((curve.apply(999.0) - 80.0).abs() < 1e-9);
+
+        let mut curve = Curve::new(
+            [(1.0, 10.0), (10.0, 30.0), (100.0, 60.0), (1000.0, 90.0)]
+                .into_iter()
+                .map(|(x, y)| CurvePoint { x, y })
+                .collect(),
+        );
+        curve.x_scale = Scale::Log;
+        assert!(curve.apply(31.0) > 30.0 && curve.apply(31.0) < 60.0);
+        assert_eq!(curve.apply(10000.0), 90.0);
+        assert_eq!(curve.apply(50000.0), 90.0);
+        assert_eq!(curve.apply(0.0), 10.0);
+    }
+
+    #[test]
+    fn deserialize() {
+        #[derive(serde::Deserialize)]
+        struct Wrapper {
+            curve: Curve,
+        }
+        let list: Wrapper = toml::from_str("curve = [{ x = 0, y = 0 }]").unwrap();
+        assert!(matches!(list.curve.x_scale, Scale::Linear));
+        let table: Wrapper =
+            toml::from_str("[curve]\nx_scale = \"log\"\npoints = [{ x = 0, y = 0 }]").unwrap();
+        assert!(matches!(table.curve.x_scale, Scale::Log));
+        assert_eq!(table.curve.points.len(), 1);
+    }
+
+    #[test]
+    fn validate() {
+        assert!(Curve::new(vec![]).validate().is_err());
+        assert!(
+            Curve::new(vec![
+                CurvePoint { x: 1.0, y: 0.0 },
+                CurvePoint { x: 1.0, y: 5.0 },
+            ])
+            .validate()
+            .is_err()
+        );
+        assert!(
+            Curve::new(vec![CurvePoint { x: 1.0, y: 0.0 }])
+                .validate()
+                .is_ok()
+        );
+    }
+
+    #[test]
+    fn units() {
+        assert_eq!(Unit::Raw.to_value(255, 42.4), 42);
+        assert_eq!(Unit::Percent.to_value(255, 50.0), 128);
+        assert_eq!(Unit::Perceptual.to_value(1000, 100.0), 1000);
+        assert_eq!(Unit::Perceptual.to_value(1000, 50.0), 184);
+        for unit in [Unit::Raw, Unit::Percent, Unit::Perceptual] {
+            for value in [0, 1, 9, 500, 1000] {
+                assert_eq!(unit.to_value(1000, unit.to_unit(1000, value)), value);
+            }
         }
-        assert_eq!(curve.apply(0), 0);
-        assert_eq!(curve.apply(100), 50);
-        assert_eq!(curve.apply(50), 25);
     }
 }
@@ -0,0 +1,550 @@
+//! Adjust brightness from ambient light sensors, see [`run`].
+
+use std::{
+    mem,
+    os::fd::AsFd,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use log::{debug, error, info, warn};
+
+use crate::{
+    Device, Iio, Limits,
+    curve::Curve,
+    error::{Error, Result},
+    fusion::Fusion,
+    inotify::Inotify,
+    ipc::Ipc,
+    lock::Lock,
+    poll::poll,
+    signal::Signals,
+    systemd::{self, Notifier},
+    target_brightness,
+    transition::{Transition, TransitionSetting},
+    uevent::{Uevent, UeventSocket},
+    value::Value,
+};
+
+/// Resolved settings for the daemon.
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct DaemonSetting {
+    /// Devices driven at once, each with its own sensors and curve.
+    pub devices: Vec<DeviceSetting>,
+    /// Milliseconds between sensor readings.
+    pub interval: u64,
+    /// Set the devices back to their original brightness on exit.
+    pub restore_on_exit: bool,
+    /// Take over from an already running daemon instead of refusing to start.
+    pub replace: bool,
+}
+
+impl DaemonSetting {
+    /// Drive `devices`, reading the sensors every second.
+    pub fn new(devices: Vec<DeviceSetting>) -> Self {
+        Self {
+            devices,
+            interval: 1000,
+            restore_on_exit: false,
+            replace: false,
+        }
+    }
+}
+
+/// Resolved settings for one device driven by the daemon.
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct DeviceSetting {
+    /// Relative to `/sys/class`, `None` for the first device found.
+    pub device: Option<PathBuf>,
+    /// How changes fade.
+    pub transition: TransitionSetting,
+    /// Empty to use the first sensor found.
+    pub iio_sensors: Vec<String>,
+    /// How readings from `iio_sensors` are combined.
+    pub fusion: Fusion,
+    /// Weights for [`Fusion::Weighted`], in the same order as `iio_sensors`.
+    pub weights: Vec<f64>,
+    /// Maps the combined illuminance to brightness.
+    pub curve: Curve,
+    /// Absolute values the brightness is kept within.
+    pub limits: Limits,
+}
+
+impl DeviceSetting {
+    /// Drive `device` from the first sensor found through `curve`, with the default transition
+    /// and no limits.
+    pub fn new(device: Option<PathBuf>, curve: Curve) -> Self {
+        Self {
+            device,
+            transition: TransitionSetting::default(),
+            iio_sensors: vec![],
+            fusion: Fusion::default(),
+            weights: vec![],
+            curve,
+            limits: Limits::default(),
+        }
+    }
+}
+
+/// A sensor that may come and go, `None` selecting the first one found.
+struct Sensor {
+    selector: Option<String>,
+    iio: Option<Iio>,
+}
+
+impl Sensor {
+    fn reopen(&mut self) {
+        if self.iio.is_some() {
+            return;
+        }
+        let iio = match &self.selector {
+            Some(x) => Iio::find(x).ok(),
+            None => Iio::first().0,
+        };
+        if let Some(iio) = iio {
+            info!("iio `{:?}` is available", iio.name());
+            self.iio = Some(iio);
+        }
+    }
+    /// Read the illuminance, closing the sensor on error so it is reopened once it's back.
+    fn read(&mut self) -> Option<f64> {
+        let iio = self.iio.as_mut()?;
+        match iio.get_illuminance() {
+            Ok(x) => Some(x),
+            Err(e) => {
+                warn!(
+                    "error while reading iio `{:?}`, waiting for it to come back: {e}",
+                    iio.name()
+                );
+                self.iio = None;
+                None
+            }
+        }
+    }
+}
+
+struct Output {
+    setting: DeviceSetting,
+    device: Option<Device>,
+    /// Brightness before the daemon first touched the device.
+    original: Option<i32>,
+    /// Indices into the daemon's sensors.
+    sensors: Vec<usize>,
+    /// The in-flight transition and when its next value is due.
+    transition: Option<(Transition, Instant)>,
+    /// Last illuminance and the brightness it was mapped to.
+    last: Option<(f64, i32)>,
+}
+
+impl Output {
+    fn reopen(&mut self) {
+        if self.device.is_some() {
+            return;
+        }
+        let device = match &self.setting.device {
+            Some(name) => Device::new(Path::new("/sys/class").join(name)).ok(),
+            None => Device::first().0,
+        };
+        if let Some(mut device) = device {
+            info!("device `{:?}` is available", device.name());
+            if self.original.is_none() {
+                self.original = device.get_brightness().ok();
+            }
+            self.device = Some(device);
+        }
+    }
+    /// Write the end of the in-flight transition right away, or the original brightness with
+    /// `restore`.
+    fn finish(&mut self, restore: bool) {
+        let transition = self.transition.take();
+        let Some(device) = &mut self.device else {
+            return;
+        };
+        let brightness = if restore {
+            self.original
+        } else {
+            transition.and_then(|(transition, _)| transition.last().map(|(x, _)| x))
+        };
+        if let Some(brightness) = brightness
+            && let Err(e) = device.set_brightness(brightness)
+        {
+            error!(
+                "error while setting brightness of `{:?}`: {e}",
+                device.name()
+            );
+        }
+    }
+    /// Close the device after an error so it is reopened once it's back.
+    fn close(&mut self, e: Error) {
+        if let Some(device) = self.device.take() {
+            warn!(
+                "error while setting brightness of `{:?}`, waiting for it to come back: {e}",
+                device.name()
+            );
+        }
+        self.transition = None;
+    }
+    fn update(&mut self, illuminance: f64, now: Instant) -> Result<()> {
+        let Some(device) = &mut self.device else {
+            return Ok(());
+        };
+        // the brightness would only be seen once the panel is back, by then a new sample is due
+        if device.get_power()? == Some(false) {
+            debug!("{:?} is powered off, leaving it alone", device.name());
+            self.transition = None;
+            return Ok(());
+        }
+        let max_brightness = device.get_max_brightness()?;
+        let y = self.setting.curve.apply(illuminance);
+        debug!("{:?}: illuminance: {illuminance}, y: {y}", device.name());
+        let (current, new) = target_brightness(
+            device,
+            Value::number(self.setting.curve.unit.to_value(max_brightness, y)),
+            &self.setting.limits,
+        )?;
+        self.transition = Some((Transition::new(current, new, &self.setting.transition), now));
+        self.last = Some((illuminance, new));
+        Ok(())
+    }
+    fn step(&mut self, now: Instant) -> Result<()> {
+        let (Some(device), Some((transition, due))) = (&mut self.device, &mut self.transition)
+        else {
+            return Ok(());
+        };
+        if now < *due {
+            return Ok(());
+        }
+        match transition.next() {
+            Some((brightness, delay)) => {
+                *due = now + delay;
+                device.set_brightness(brightness)
+            }
+            None => {
+                self.transition = None;
+                Ok(())
+            }
+        }
+    }
+}
+
+pub(crate) struct Daemon {
+    pub(crate) interval: Duration,
+    restore_on_exit: bool,
+    sensors: Vec<Sensor>,
+    outputs: Vec<Output>,
+    pub(crate) paused: bool,
+}
+
+impl Daemon {
+    pub(crate) fn new(setting: DaemonSetting) -> Self {
+        let mut daemon = Self {
+            interval: Duration::ZERO,
+            restore_on_exit: false,
+            sensors: vec![],
+            outputs: vec![],
+            paused: false,
+        };
+        daemon.configure(setting);
+        daemon
+    }
+    /// Apply `setting`, keeping the open devices, sensors and in-flight transitions that are
+    /// still in use.
+    pub(crate) fn configure(&mut self, setting: DaemonSetting) {
+        self.interval = Duration::from_millis(setting.interval);
+        self.restore_on_exit = setting.restore_on_exit;
+        let mut old_sensors = mem::take(&mut self.sensors);
+        let mut old_outputs = mem::take(&mut self.outputs);
+        for setting in setting.devices {
+            let selectors = if setting.iio_sensors.is_empty() {
+                vec![None]
+            } else {
+                setting.iio_sensors.iter().cloned().map(Some).collect()
+            };
+            let mut indices = vec![];
+            for selector in selectors {
+                if let Some(i) = self.sensors.iter().position(|x| x.selector == selector) {
+                    indices.push(i);
+                    continue;
+                }
+                let sensor = match old_sensors.iter().position(|x| x.selector == selector) {
+                    Some(i) => old_sensors.swap_remove(i),
+                    None => {
+                        let mut sensor = Sensor {
+                            selector,
+                            iio: None,
+                        };
+                        sensor.reopen();
+                        if sensor.iio.is_none() {
+                            warn!("iio `{:?}` not found, waiting for it", sensor.selector);
+                        }
+                        sensor
+                    }
+                };
+                self.sensors.push(sensor);
+                indices.push(self.sensors.len() - 1);
+            }
+            let output = match old_outputs
+                .iter()
+                .position(|x| x.setting.device == setting.device)
+            {
+                Some(i) => {
+                    let mut output = old_outputs.swap_remove(i);
+                    output.setting = setting;
+                    output.sensors = indices;
+                    output
+                }
+                None => {
+                    let mut output = Output {
+                        setting,
+                        device: None,
+                        original: None,
+                        sensors: indices,
+                        transition: None,
+                        last: None,
+                    };
+                    output.reopen();
+                    if output.device.is_none() {
+                        warn!(
+                            "device `{:?}` not found, waiting for it",
+                            output.setting.device
+                        );
+                    }
+                    output
+                }
+            };
+            self.outputs.push(output);
+        }
+        for mut output in old_outputs {
+            output.finish(self.restore_on_exit);
+        }
+    }
+    pub(crate) fn sample(&mut self, now: Instant) {
+        self.sensors.iter_mut().for_each(Sensor::reopen);
+        self.outputs.iter_mut().for_each(Output::reopen);
+        let illuminances = self
+            .sensors
+            .iter_mut()
+            .map(Sensor::read)
+            .collect::<Vec<_>>();
+        for output in &mut self.outputs {
+            let readings = output
+                .sensors
+                .iter()
+                .map(|&i| illuminances[i])
+                .collect::<Vec<_>>();
+            let Some(illuminance) = output
+                .setting
+                .fusion
+                .combine(&readings, &output.setting.weights)
+            else {
+                continue;
+            };
+            if let Err(e) = output.update(illuminance, now) {
+                output.close(e);
+            }
+        }
+    }
+    pub(crate) fn step(&mut self, now: Instant) {
+        for output in &mut self.outputs {
+            if let Err(e) = output.step(now) {
+                output.close(e);
+            }
+        }
+    }
+    /// When the loop has to wake up next, given when the next sample is due.
+    pub(crate) fn deadline(&self, next_sample: Instant) -> Instant {
+        self.outputs
+            .iter()
+            .filter_map(|x| x.transition.as_ref().map(|(_, due)| *due))
+            .fold(next_sample, Instant::min)
+    }
+    /// One line per device with the last illuminance and brightness, for `STATUS=` and ipc.
+    pub(crate) fn status(&self) -> String {
+        if self.paused {
+            return "paused".to_owned();
+        }
+        self.outputs
+            .iter()
+            .map(|x| {
+                let name = match &x.device {
+                    Some(device) => format!("{:?}", device.name()),
+                    None => format!("{:?} (missing)", x.setting.device),
+                };
+                match x.last {
+                    Some((illuminance, brightness)) => {
+                        format!(
+                            "{name}: illuminance: {illuminance:.2} lux, brightness: {brightness}"
+                        )
+                    }
+                    None => name,
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("; ")
+    }
+    pub(crate) fn shutdown(&mut self) {
+        for output in &mut self.outputs {
+            output.finish(self.restore_on_exit);
+        }
+    }
+}
+
+/// Whether `uevents` include a device or sensor that may have appeared. `change` is left out,
+/// since every write to `brightness` emits one.
+pub(crate) fn is_hotplug(uevents: &[Uevent]) -> bool {
+    uevents.iter().any(|x| {
+        x.is_overflow()
+            || matches!(x.subsystem.as_str(), "backlight" | "leds" | "iio")
+                && matches!(x.action.as_str(), "add" | "bind")
+    })
+}
+
+/// Sample the sensors every `interval` and fade every device towards its curve's output. All
+/// devices share one loop, so a long transition on one doesn't hold back the others.
+///
+/// Devices and sensors that are missing or disappear are skipped until they come back, which is
+/// noticed through kernel uevents or, failing that, on the next sample. Backlights powered off
+/// through `bl_power` are left alone until they are powered on again.
+///
+/// SIGTERM and SIGINT jump in-flight transitions to their end (or restore the brightness from
+/// before the daemon started, with `restore_on_exit`) and return, SIGHUP applies the setting
+/// returned by `reload` and SIGUSR1 toggles pausing. `reload` is also called whenever the file
+/// at `config_path` is written, and returns `None` to keep the current setting. The same can be
+/// requested through the [`Ipc`] socket.
+///
+/// Only one daemon runs per user, see [`Lock`].
+///
+/// Under systemd, readiness, status and watchdog pings are sent to `$NOTIFY_SOCKET`.
+pub fn run(
+    setting: DaemonSetting,
+    config_path: &Path,
+    mut reload: impl FnMut() -> Option<DaemonSetting>,
+) -> Result<()> {
+    let _lock = Lock::acquire(setting.replace)?;
+    let signals = Signals::new(&[libc::SIGTERM, libc::SIGINT, libc::SIGHUP, libc::SIGUSR1])?;
+    let mut uevents = UeventSocket::new()
+        .inspect_err(|e| warn!("cannot listen for uevents, polling instead: {e}"))
+        .ok();
+    let config_watch = Inotify::watch(config_path)
+        .inspect_err(|e| warn!("cannot watch the config file `{config_path:?}`: {e}"))
+        .ok();
+    let ipc = Ipc::new()
+        .inspect_err(|e| warn!("cannot listen on the ipc socket: {e}"))
+        .ok();
+    let notifier = Notifier::from_env().and_then(|x| {
+        x.inspect_err(|e| warn!("cannot connect to NOTIFY_SOCKET: {e}"))
+            .ok()
+    });
+    let notify = |state: &str| {
+        if let Some(notifier) = &notifier
+            && let Err(e) = notifier.notify(state)
+        {
+            warn!("error while notifying the service manager: {e}");
+        }
+    };
+    // ping at twice the rate systemd expects, as sd_watchdog_enabled(3) recommends
+    let watchdog_interval = systemd::watchdog_interval().map(|x| x / 2);
+
+    let mut daemon = Daemon::new(setting);
+    notify(&format!("READY=1\nSTATUS={}", daemon.status()));
+
+    let mut next_sample = Instant::now();
+    let mut next_watchdog = Instant::now();
+    loop {
+        let now = Instant::now();
+        if now >= next_sample {
+            if !daemon.paused {
+                daemon.sample(now);
+                notify(&format!("STATUS={}", daemon.status()));
+            }
+            next_sample = now + daemon.interval;
+        }
+        daemon.step(now);
+        if let Some(watchdog_interval) = watchdog_interval
+            && now >= next_watchdog
+        {
+            notify("WATCHDOG=1");
+            next_watchdog = now + watchdog_interval;
+        }
+
+        let mut deadline = daemon.deadline(next_sample);
+        if watchdog_interval.is_some() {
+            deadline = deadline.min(next_watchdog);
+        }
+        let mut fds = vec![signals.as_fd()];
+        fds.extend(uevents.as_ref().map(AsFd::as_fd));
+        fds.extend(config_watch.as_ref().map(AsFd::as_fd));
+        fds.extend(ipc.as_ref().map(AsFd::as_fd));
+        poll(&fds, deadline.saturating_duration_since(Instant::now()))?;
+
+        let mut reload_requested = match &config_watch {
+            Some(config_watch) => config_watch.changed()?,
+            None => false,
+        };
+        let mut toggle_pause = false;
+
+        for signal in signals.read()? {
+            match signal {
+                libc::SIGTERM | libc::SIGINT => {
+                    notify("STOPPING=1");
+                    daemon.shutdown();
+                    return Ok(());
+                }
+                libc::SIGHUP => reload_requested = true,
+                libc::SIGUSR1 => toggle_pause = !toggle_pause,
+                _ => {}
+            }
+        }
+        for request in ipc
+            .as_ref()
+            .map(Ipc::accept)
+            .transpose()?
+            .unwrap_or_default()
+        {
+            let reply = match request.command.as_str() {
+                "status" => daemon.status(),
+                "pause" | "resume" | "toggle" => {
+                    toggle_pause = match request.command.as_str() {
+                        "pause" => !daemon.paused,
+                        "resume" => daemon.paused,
+                        _ => !toggle_pause,
+                    };
+                    "ok".to_owned()
+                }
+                "reload" => {
+                    reload_requested = true;
+                    "ok".to_owned()
+                }
+                command => format!("error: unknown command `{command}`"),
+            };
+            request.reply(&reply);
+        }
+
+        if toggle_pause {
+            daemon.paused = !daemon.paused;
+            next_sample = Instant::now();
+            info!("{}", if daemon.paused { "paused" } else { "resumed" });
+            notify(&format!("STATUS={}", daemon.status()));
+        }
+        if reload_requested && let Some(setting) = reload() {
+            daemon.configure(setting);
+            next_sample = Instant::now();
+            info!("config reloaded");
+        }
+        if let Some(socket) = &uevents {
+            match socket.recv() {
+                Ok(received) if is_hotplug(&received) => {
+                    // take a new sample right away with whatever appeared
+                    next_sample = Instant::now();
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    warn!("cannot receive uevents, polling instead: {e}");
+                    uevents = None;
+                }
+            }
+        }
+    }
+}
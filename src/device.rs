@@ -0,0 +1,421 @@
+use std::{
+    fs::{self, File},
+    io::{self, Read, Seek, Write},
+    os::unix::fs::MetadataExt,
+    path::{Path, PathBuf},
+    str::FromStr,
+    thread,
+};
+
+use log::{debug, warn};
+
+use crate::{
+    error::{Error, Result},
+    transition::{Transition, TransitionSetting},
+    value::{Prefix, Type, Value, to_value},
+};
+
+/// A backlight or led in `/sys/class`, with its `brightness` open for writing.
+///
+/// The device is reopened when its sysfs node has been recreated since it was opened, e.g. when
+/// its driver is rebound.
+#[derive(Debug)]
+pub struct Device {
+    name: PathBuf,
+    path: PathBuf,
+    brightness: File,
+    /// Inode of `brightness`, which changes when the node is recreated.
+    inode: u64,
+    max_brightness: i32,
+}
+
+impl Device {
+    /// Open the device at `dir_path`, e.g. `/sys/class/backlight/intel_backlight`.
+    pub fn new<P>(dir_path: P) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let path = dir_path.as_ref().to_path_buf();
+        if !path.exists() {
+            return Err(Error::MissingDevice { path: Some(path) });
+        }
+        let brightness_path = path.join("brightness");
+        let brightness = File::options()
+            .read(true)
+            .write(true)
+            .open(&brightness_path)
+            .map_err(|e| Error::io(&brightness_path, e))?;
+        Ok(Self {
+            name: path
+                .strip_prefix("/sys/class/")
+                .unwrap_or(&path)
+                .to_path_buf(),
+            inode: brightness
+                .metadata()
+                .map_err(|e| Error::io(&brightness_path, e))?
+                .ino(),
+            brightness,
+            max_brightness: read_attribute_once(&path.join("max_brightness"))?,
+            path,
+        })
+    }
+    /// Path relative to `/sys/class`, e.g. `backlight/intel_backlight`.
+    pub fn name(&self) -> &Path {
+        &self.name
+    }
+    /// Path of the device's directory.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+    /// Every device in `/sys/class/backlight` and `/sys/class/leds`, and the errors from the
+    /// ones that couldn't be opened.
+    pub fn all() -> (Vec<Self>, Vec<Error>) {
+        ["/sys/class/backlight", "/sys/class/leds"]
+            .into_iter()
+            .fold((vec![], vec![]), |(mut devices, mut errors), dir| {
+                match fs::read_dir(dir) {
+                    Ok(dir) => {
+                        for device in dir {
+                            match device
+                                .map_err(Error::from)
+                                .and_then(|x| Self::new(x.path()))
+                            {
+                                Ok(x) => devices.push(x),
+                                Err(e) => errors.push(e),
+                            }
+                        }
+                    }
+                    Err(e) => errors.push(Error::io(dir, e)),
+                };
+                (devices, errors)
+            })
+    }
+    /// The first device that could be opened, backlights first, and the errors from the ones
+    /// before it.
+    pub fn first() -> (Option<Self>, Vec<Error>) {
+        let mut errors = vec![];
+        for dir in ["/sys/class/backlight", "/sys/class/leds"] {
+            match fs::read_dir(dir) {
+                Ok(dir) => {
+                    for device in dir {
+                        match device
+                            .map_err(Error::from)
+                            .and_then(|x| Self::new(x.path()))
+                        {
+                            Ok(x) => return (Some(x), errors),
+                            Err(e) => errors.push(e),
+                        }
+                    }
+                }
+                Err(e) => errors.push(Error::io(dir, e)),
+            }
+        }
+        (None, errors)
+    }
+    /// Reopen the device if its node was recreated since it was opened, failing with
+    /// [`Error::MissingDevice`] while it's gone.
+    fn revalidate(&mut self) -> Result<()> {
+        let brightness_path = self.path.join("brightness");
+        match fs::metadata(&brightness_path) {
+            Ok(metadata) if metadata.ino() == self.inode => Ok(()),
+            Ok(_) => {
+                debug!("`{}` was recreated, reopening it", self.name.display());
+                *self = Self::new(&self.path)?;
+                Ok(())
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Err(Error::MissingDevice {
+                path: Some(self.path.clone()),
+            }),
+            Err(e) => Err(Error::io(brightness_path, e)),
+        }
+    }
+    /// Current raw brightness.
+    pub fn get_brightness(&mut self) -> Result<i32> {
+        self.revalidate()?;
+        read_attribute(&mut self.brightness, &self.path.join("brightness"))
+    }
+    /// Write a raw brightness right away, negative values writing 0.
+    pub fn set_brightness(&mut self, new_brightness: i32) -> Result<()> {
+        self.revalidate()?;
+        self.brightness
+            .write_all(new_brightness.max(0).to_string().as_bytes())
+            .map_err(|e| Error::io(self.path.join("brightness"), e))
+    }
+    /// Raw brightness reported by the hardware, which can differ from the requested
+    /// [`get_brightness`](Self::get_brightness), e.g. while the firmware fades or limits it.
+    /// Leds have no `actual_brightness`, so their `brightness` is returned instead.
+    pub fn get_actual_brightness(&mut self) -> Result<i32> {
+        self.revalidate()?;
+        let path = self.path.join("actual_brightness");
+        if !path.exists() {
+            return read_attribute(&mut self.brightness, &self.path.join("brightness"));
+        }
+        read_attribute_once(&path)
+    }
+    /// Whether the backlight is powered on according to `bl_power`, `None` for devices without
+    /// it such as leds.
+    pub fn get_power(&mut self) -> Result<Option<bool>> {
+        self.revalidate()?;
+        let path = self.path.join("bl_power");
+        if !path.exists() {
+            return Ok(None);
+        }
+        // 0 is `FB_BLANK_UNBLANK`, anything else blanks the panel in some way
+        read_attribute_once::<i32>(&path).map(|x| Some(x == 0))
+    }
+    /// Power the backlight on or off through `bl_power`, keeping its brightness.
+    pub fn set_power(&mut self, on: bool) -> Result<()> {
+        self.revalidate()?;
+        let path = self.path.join("bl_power");
+        if !path.exists() {
+            return Err(Error::InvalidValue {
+                value: if on { "on" } else { "off" }.to_owned(),
+                reason: format!(
+                    "`{}` has no `bl_power`, only backlights can be powered off",
+                    self.name.display()
+                ),
+            });
+        }
+        // `FB_BLANK_UNBLANK` or `FB_BLANK_POWERDOWN`
+        File::options()
+            .write(true)
+            .open(&path)
+            .and_then(|mut file| file.write_all(if on { b"0" } else { b"4" }))
+            .map_err(|e| Error::io(&path, e))
+    }
+    /// Highest raw brightness, read when the device was opened.
+    pub fn get_max_brightness(&mut self) -> Result<i32> {
+        self.revalidate()?;
+        Ok(self.max_brightness)
+    }
+}
+
+/// Read a numeric sysfs attribute from the start of `file`, opened from `path`.
+pub(crate) fn read_attribute<T: FromStr>(file: &mut File, path: &Path) -> Result<T> {
+    let mut buf = String::new();
+    file.rewind()
+        .and_then(|()| file.read_to_string(&mut buf))
+        .map_err(|e| Error::io(path, e))?;
+    buf.trim().parse().map_err(|_| Error::Parse {
+        path: path.to_path_buf(),
+        content: buf,
+    })
+}
+
+/// Read a numeric sysfs attribute that is only read once.
+pub(crate) fn read_attribute_once<T: FromStr>(path: &Path) -> Result<T> {
+    let buf = fs::read_to_string(path).map_err(|e| Error::io(path, e))?;
+    buf.trim().parse().map_err(|_| Error::Parse {
+        path: path.to_path_buf(),
+        content: buf,
+    })
+}
+
+/// Open `device`, or the first device found. When none could be opened because of
+/// permissions, that error is returned as it is more helpful than not finding any device.
+pub fn open_device(device: Option<PathBuf>) -> Result<Device> {
+    match device {
+        Some(name) => Device::new(Path::new("/sys/class").join(name)),
+        None => {
+            let (device, errors) = Device::first();
+            if let Some(device) = device {
+                for e in errors {
+                    warn!("error while getting first device: {e}");
+                }
+                return Ok(device);
+            }
+            let mut denied = None;
+            for e in errors {
+                match e {
+                    Error::PermissionDenied { .. } if denied.is_none() => denied = Some(e),
+                    e => debug!("error while getting first device: {e}"),
+                }
+            }
+            Err(denied.unwrap_or(Error::MissingDevice { path: None }))
+        }
+    }
+}
+
+/// Percent of `max` that `value` is, rounded to as many decimals as `step` has so that
+/// repeated relative changes stay on the same grid.
+fn percentage_at(max: i32, value: i32, step: f64) -> f64 {
+    let mut scale = 1.0;
+    while (step * scale).fract() != 0.0 && scale < 1000.0 {
+        scale *= 10.0;
+    }
+    (value as f64 * 100.0 / max as f64 * scale).round() / scale
+}
+
+/// Absolute values the brightness is kept within, besides `0` and `max_brightness`.
+#[derive(Clone, Debug, Default)]
+#[non_exhaustive]
+pub struct Limits {
+    /// Lowest brightness set, e.g. `5%` or `10`.
+    pub min: Option<Value>,
+    /// Highest brightness set, e.g. `80%` or `200`.
+    pub max: Option<Value>,
+}
+
+impl Limits {
+    /// Keep the brightness between `min` and `max`, when given.
+    pub fn new(min: Option<Value>, max: Option<Value>) -> Self {
+        Self { min, max }
+    }
+}
+
+/// Resolve `value` against the device's current brightness, returning the current and the
+/// new brightness, clamped to `max_brightness` and `limits`.
+pub fn target_brightness(
+    device: &mut Device,
+    Value {
+        prefix,
+        r#type,
+        num,
+    }: Value,
+    limits: &Limits,
+) -> Result<(i32, i32)> {
+    let (min, max) = (limits.min.as_ref(), limits.max.as_ref());
+    let max_brightness = device.get_max_brightness()?;
+    if max_brightness <= 0 {
+        return Err(Error::InvalidValue {
+            value: max_brightness.to_string(),
+            reason: format!(
+                "`max_brightness` of `{}` must be positive",
+                device.path.display()
+            ),
+        });
+    }
+    let limit = |value: &Value| match value.r#type {
+        Type::Number => value.num.round() as i32,
+        Type::Percentage => to_value(max_brightness, value.num),
+    };
+    if let (Some(min), Some(max)) = (min, max)
+        && limit(min) > limit(max)
+    {
+        return Err(Error::InvalidValue {
+            value: min.to_string(),
+            reason: format!(
+                "the `min` limit is above the `max` limit of {max} for `{}`",
+                device.path.display()
+            ),
+        });
+    }
+    let current_brightness = device.get_brightness()?;
+    let new_brightness = match prefix {
+        Prefix::None => match r#type {
+            Type::Number => num.round() as i32,
+            Type::Percentage => to_value(max_brightness, num),
+        },
+        Prefix::Plus | Prefix::Minus => {
+            let num = match prefix {
+                Prefix::Minus => -num,
+                _ => num,
+            };
+            match r#type {
+                // in f64, as a huge `num` would overflow an i32 before being clamped
+                Type::Number => (current_brightness as f64 + num.round()) as i32,
+                Type::Percentage => to_value(
+                    max_brightness,
+                    percentage_at(max_brightness, current_brightness, num) + num,
+                ),
+            }
+        }
+        Prefix::Multiply => (current_brightness as f64 * num).round() as i32,
+        Prefix::Divide => (current_brightness as f64 / num).round() as i32,
+    }
+    .min(max.map_or(max_brightness, limit).min(max_brightness))
+    .max(min.map_or(0, limit));
+    Ok((current_brightness, new_brightness))
+}
+
+/// Change the brightness to `value` within `limits`, fading as `transition` says. Blocks until
+/// done.
+pub fn set_brightness(
+    device: &mut Device,
+    value: Value,
+    transition: &TransitionSetting,
+    limits: &Limits,
+) -> Result<()> {
+    let (current_brightness, new_brightness) = target_brightness(device, value, limits)?;
+    let transition = Transition::new(current_brightness, new_brightness, transition);
+    for (brightness, delay) in transition {
+        device.set_brightness(brightness)?;
+        thread::sleep(delay);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use super::{Device, Limits, percentage_at, target_brightness};
+    use crate::{error::Error, temp_dir::TempDir};
+
+    /// A fake device at brightness 5 of 10.
+    fn device_dir(name: &str) -> TempDir {
+        let dir = TempDir::new(name);
+        fs::write(dir.join("brightness"), "5\n").unwrap();
+        fs::write(dir.join("max_brightness"), "10\n").unwrap();
+        dir
+    }
+
+    #[test]
+    fn percentage_grid() {
+        assert_eq!(percentage_at(1875, 938, 10.0), 50.0);
+        assert_eq!(percentage_at(1875, 47, 2.5), 2.5);
+        assert_eq!(percentage_at(1875, 94, 2.5), 5.0);
+    }
+
+    #[test]
+    fn recreated() {
+        let dir = device_dir("device");
+        let mut device = Device::new(&dir).unwrap();
+        assert_eq!(device.get_max_brightness().unwrap(), 10);
+
+        // like a driver rebind, with new nodes
+        fs::write(dir.join("max_brightness"), "20\n").unwrap();
+        fs::write(dir.join("brightness.new"), "7\n").unwrap();
+        fs::rename(dir.join("brightness.new"), dir.join("brightness")).unwrap();
+        assert_eq!(device.get_brightness().unwrap(), 7);
+        assert_eq!(device.get_max_brightness().unwrap(), 20);
+
+        fs::remove_dir_all(&*dir).unwrap();
+        assert!(matches!(
+            device.get_brightness(),
+            Err(Error::MissingDevice { .. })
+        ));
+    }
+
+    #[test]
+    fn power() {
+        let dir = device_dir("device-power");
+        let mut device = Device::new(&dir).unwrap();
+        assert_eq!(device.get_actual_brightness().unwrap(), 5);
+        assert_eq!(device.get_power().unwrap(), None);
+        assert!(device.set_power(false).is_err());
+
+        fs::write(dir.join("actual_brightness"), "3\n").unwrap();
+        fs::write(dir.join("bl_power"), "0\n").unwrap();
+        assert_eq!(device.get_actual_brightness().unwrap(), 3);
+        assert_eq!(device.get_power().unwrap(), Some(true));
+        device.set_power(false).unwrap();
+        assert_eq!(device.get_power().unwrap(), Some(false));
+    }
+
+    #[test]
+    fn huge_values() {
+        let dir = device_dir("device-huge");
+        let mut device = Device::new(&dir).unwrap();
+        for (value, expected) in [
+            ("+99999999999", 10),
+            ("-99999999999", 0),
+            ("*99999999999", 10),
+        ] {
+            assert_eq!(
+                target_brightness(&mut device, value.parse().unwrap(), &Limits::default()).unwrap(),
+                (5, expected)
+            );
+        }
+    }
+}
@@ -0,0 +1,314 @@
+use std::{
+    env,
+    ffi::{CStr, CString},
+    fs, io,
+    os::unix::{ffi::OsStrExt, fs::MetadataExt},
+    path::{Path, PathBuf},
+};
+
+use serde::Serialize;
+
+use lilight::{Device, Error, Iio, config::Config, lock, open_device};
+
+/// Name of the group `gid`, or the number itself if it has none.
+fn group_name(gid: libc::gid_t) -> String {
+    let group = unsafe { libc::getgrgid(gid) };
+    if group.is_null() {
+        return gid.to_string();
+    }
+    unsafe { CStr::from_ptr((*group).gr_name) }
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Whether the current process is in the group `gid`.
+fn in_group(gid: libc::gid_t) -> bool {
+    if unsafe { libc::getegid() } == gid {
+        return true;
+    }
+    let n = unsafe { libc::getgroups(0, std::ptr::null_mut()) };
+    let mut groups = vec![0; n.max(0) as usize];
+    let n = unsafe { libc::getgroups(groups.len() as i32, groups.as_mut_ptr()) };
+    groups[..n.max(0) as usize].contains(&gid)
+}
+
+/// Why the current user can't write to the `brightness` attribute at `path`.
+fn explain(path: &Path) -> String {
+    let metadata = match fs::metadata(path) {
+        Ok(x) => x,
+        Err(e) => return format!("cannot stat it: {e}"),
+    };
+    let group = group_name(metadata.gid());
+    if metadata.mode() & 0o020 == 0 {
+        format!(
+            "it is only writable by its owner (group `{group}`, mode {:o}), install a udev rule \
+             with `sudo lilight udev-rule --install`",
+            metadata.mode() & 0o777
+        )
+    } else if !in_group(metadata.gid()) {
+        format!(
+            "it is writable by the `{group}` group which you are not in, add yourself with \
+             `sudo usermod -aG {group} $USER` and log in again"
+        )
+    } else {
+        "the file permissions allow writing, something else such as a security module denies it"
+            .to_owned()
+    }
+}
+
+/// Whether the current user may access `path` with `mode`, e.g. `libc::W_OK`.
+fn access(path: &Path, mode: libc::c_int) -> bool {
+    let Ok(path) = CString::new(path.as_os_str().as_bytes()) else {
+        return false;
+    };
+    unsafe { libc::access(path.as_ptr(), mode) == 0 }
+}
+
+#[derive(Serialize)]
+struct ConfigReport {
+    path: PathBuf,
+    found: bool,
+    /// Why the config file can't be used, the defaults apply then.
+    error: Option<String>,
+}
+
+#[derive(Serialize)]
+struct DeviceReport {
+    name: PathBuf,
+    readable: bool,
+    writable: bool,
+    /// What's missing for `writable`.
+    hint: Option<String>,
+}
+
+#[derive(Serialize)]
+struct SensorReport {
+    name: PathBuf,
+    path: PathBuf,
+    device_name: Option<String>,
+    label: Option<String>,
+    channel_files: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct DaemonReport {
+    running: bool,
+    pid: Option<libc::pid_t>,
+    error: Option<String>,
+}
+
+#[derive(Serialize)]
+struct BackendReport {
+    name: &'static str,
+    available: bool,
+    detail: String,
+}
+
+/// Everything worth knowing when lilight doesn't seem to do anything.
+#[derive(Serialize)]
+struct Report {
+    config: ConfigReport,
+    devices: Vec<DeviceReport>,
+    /// Errors from opening the devices.
+    device_errors: Vec<String>,
+    /// The device used when none is given.
+    default_device: Option<PathBuf>,
+    /// Why there is no default device.
+    default_device_error: Option<String>,
+    sensors: Vec<SensorReport>,
+    /// Errors from opening the sensors, including iio devices that aren't light sensors.
+    sensor_errors: Vec<String>,
+    daemon: DaemonReport,
+    backends: Vec<BackendReport>,
+}
+
+impl Report {
+    fn new(config_path: &Path) -> Self {
+        let (config, config_report) = match Config::read(config_path) {
+            Ok(config) => (
+                config,
+                ConfigReport {
+                    path: config_path.to_path_buf(),
+                    found: true,
+                    error: None,
+                },
+            ),
+            Err(e) => (
+                Default::default(),
+                ConfigReport {
+                    path: config_path.to_path_buf(),
+                    found: config_path.exists(),
+                    error: match e {
+                        Error::Io { source, .. } if source.kind() == io::ErrorKind::NotFound => {
+                            None
+                        }
+                        e => Some(e.to_string()),
+                    },
+                },
+            ),
+        };
+
+        let mut devices = vec![];
+        for class in ["/sys/class/backlight", "/sys/class/leds"] {
+            let Ok(dir) = fs::read_dir(class) else {
+                continue;
+            };
+            for entry in dir.flatten() {
+                let brightness = entry.path().join("brightness");
+                let writable = access(&brightness, libc::W_OK);
+                devices.push(DeviceReport {
+                    name: Path::new(class)
+                        .file_name()
+                        .map(|x| Path::new(x).join(entry.file_name()))
+                        .unwrap_or(entry.path()),
+                    readable: access(&brightness, libc::R_OK),
+                    writable,
+                    hint: (!writable).then(|| explain(&brightness)),
+                });
+            }
+        }
+        devices.sort_by(|a, b| a.name.cmp(&b.name));
+        let (_, device_errors) = Device::all();
+
+        let (iios, sensor_errors) = Iio::all();
+        let sensors = iios
+            .iter()
+            .map(|iio| SensorReport {
+                name: iio.name().to_path_buf(),
+                path: iio.path().to_path_buf(),
+                device_name: iio.device_name().map(str::to_owned),
+                label: iio.label().map(str::to_owned),
+                channel_files: iio.channel_files().unwrap_or_default(),
+            })
+            .collect();
+
+        let daemon = match lock::running_pid() {
+            Ok(pid) => DaemonReport {
+                running: pid.is_some(),
+                pid: pid.flatten(),
+                error: None,
+            },
+            Err(e) => DaemonReport {
+                running: false,
+                pid: None,
+                error: Some(e.to_string()),
+            },
+        };
+
+        let sysfs = devices.iter().filter(|x| x.writable).count();
+        let logind =
+            env::var_os("XDG_SESSION_ID").is_some() && Path::new("/run/systemd/seats").exists();
+        let backends = vec![
+            BackendReport {
+                name: "sysfs",
+                available: sysfs != 0,
+                detail: format!("{sysfs} of {} devices writable", devices.len()),
+            },
+            BackendReport {
+                name: "logind",
+                available: logind,
+                detail: "not supported by lilight yet".to_owned(),
+            },
+        ];
+
+        let (default_device, default_device_error) = match open_device(config.default_device) {
+            Ok(x) => (Some(x.name().to_path_buf()), None),
+            Err(e) => (None, Some(e.to_string())),
+        };
+
+        Self {
+            config: config_report,
+            default_device,
+            default_device_error,
+            devices,
+            device_errors: device_errors.iter().map(Error::to_string).collect(),
+            sensors,
+            sensor_errors: sensor_errors.iter().map(Error::to_string).collect(),
+            daemon,
+            backends,
+        }
+    }
+    fn print(&self) {
+        println!("config: {}", self.config.path.display());
+        match (&self.config.error, self.config.found) {
+            (None, true) => println!("  ok"),
+            (None, false) => println!("  not found, using the defaults"),
+            (Some(e), _) => println!("  {e}, using the defaults"),
+        }
+        println!("devices:");
+        for device in &self.devices {
+            println!(
+                "  {}: {}, {}",
+                device.name.display(),
+                if device.readable {
+                    "readable"
+                } else {
+                    "not readable"
+                },
+                if device.writable {
+                    "writable"
+                } else {
+                    "not writable"
+                },
+            );
+            if let Some(hint) = &device.hint {
+                println!("    {hint}");
+            }
+        }
+        for e in &self.device_errors {
+            println!("  error: {e}");
+        }
+        match (&self.default_device, &self.default_device_error) {
+            (Some(x), _) => println!("default device: {}", x.display()),
+            (None, Some(e)) => println!("default device: none, {e}"),
+            (None, None) => println!("default device: none"),
+        }
+        println!("sensors:");
+        for sensor in &self.sensors {
+            println!(
+                "  {}: {}",
+                sensor.name.display(),
+                sensor.channel_files.join(", ")
+            );
+        }
+        for e in &self.sensor_errors {
+            println!("  error: {e}");
+        }
+        match &self.daemon {
+            DaemonReport { error: Some(e), .. } => println!("daemon: unknown, {e}"),
+            DaemonReport {
+                running: true,
+                pid: Some(pid),
+                ..
+            } => println!("daemon: running (pid {pid})"),
+            DaemonReport { running: true, .. } => println!("daemon: running"),
+            DaemonReport { .. } => println!("daemon: not running"),
+        }
+        println!("backends:");
+        for backend in &self.backends {
+            println!(
+                "  {}: {}, {}",
+                backend.name,
+                if backend.available {
+                    "available"
+                } else {
+                    "not available"
+                },
+                backend.detail
+            );
+        }
+    }
+}
+
+/// Report the config, devices, sensors, daemon and backends, as JSON with `json`.
+pub fn run(config_path: &Path, json: bool) {
+    let report = Report::new(config_path);
+    if json {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&report).expect("the report is valid JSON")
+        );
+    } else {
+        report.print();
+    }
+}
@@ -0,0 +1,182 @@
+use std::{
+    fmt, io,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+/// Result of everything fallible in lilight.
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Everything that can go wrong, with the path involved and a hint on how to fix it where
+/// there is one. Each kind of error exits with its own code, see [`Error::exit_code`].
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// The device at `path` doesn't exist, or no device was found at all with `None`.
+    MissingDevice {
+        /// The device's directory.
+        path: Option<PathBuf>,
+    },
+    /// Access to `path` was denied, usually when opening `brightness` for writing.
+    PermissionDenied {
+        /// The file or directory that couldn't be accessed.
+        path: PathBuf,
+    },
+    /// The attribute at `path` doesn't hold a number.
+    Parse {
+        /// The attribute's file.
+        path: PathBuf,
+        /// What was read instead.
+        content: String,
+    },
+    /// A value that parses but can't be used.
+    InvalidValue {
+        /// The value as given or read.
+        value: String,
+        /// Why it can't be used.
+        reason: String,
+    },
+    /// No sensor matches `selector`, or no sensor was found at all with `None`.
+    MissingSensor {
+        /// How the sensor was asked for, see [`Iio::find`](crate::Iio::find).
+        selector: Option<String>,
+        /// Descriptions of the sensors found instead.
+        available: Vec<String>,
+    },
+    /// The config file can't be parsed or has unusable values.
+    Config {
+        /// The config file.
+        path: PathBuf,
+        /// What's wrong with it.
+        reason: String,
+    },
+    /// Any other I/O error.
+    Io {
+        /// The file involved, if any.
+        path: Option<PathBuf>,
+        /// The underlying error.
+        source: io::Error,
+    },
+}
+
+impl Error {
+    /// Attach `path` to `source`, telling permission errors apart.
+    pub fn io(path: impl AsRef<Path>, source: io::Error) -> Self {
+        let path = path.as_ref().to_path_buf();
+        match source.kind() {
+            io::ErrorKind::PermissionDenied => Self::PermissionDenied { path },
+            _ => Self::Io {
+                path: Some(path),
+                source,
+            },
+        }
+    }
+    /// `1` for I/O errors, `2` is left to command line errors, then `3` to `8` in the order of
+    /// the variants.
+    pub fn exit_code(&self) -> ExitCode {
+        ExitCode::from(match self {
+            Self::Io { .. } => 1,
+            Self::MissingDevice { .. } => 3,
+            Self::PermissionDenied { .. } => 4,
+            Self::Parse { .. } => 5,
+            Self::InvalidValue { .. } => 6,
+            Self::MissingSensor { .. } => 7,
+            Self::Config { .. } => 8,
+        })
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingDevice { path: Some(path) } => write!(
+                f,
+                "device `{}` not found\nhint: run `lilight list` to see the available devices",
+                path.display()
+            ),
+            Self::MissingDevice { path: None } => write!(
+                f,
+                "no device found in `/sys/class/backlight` or `/sys/class/leds`"
+            ),
+            Self::PermissionDenied { path }
+                if path.ends_with("brightness") || path.ends_with("bl_power") =>
+            {
+                write!(
+                    f,
+                    "permission denied on `{}`\nhint: install a udev rule with `sudo lilight \
+                     udev-rule --install` and add yourself to the `video` group. `lilight \
+                     doctor` tells what's missing",
+                    path.display()
+                )
+            }
+            Self::PermissionDenied { path } => {
+                write!(f, "permission denied on `{}`", path.display())
+            }
+            Self::Parse { path, content } => write!(
+                f,
+                "cannot parse `{}`: expected a number, found `{}`",
+                path.display(),
+                content.trim()
+            ),
+            Self::InvalidValue { value, reason } => write!(f, "invalid value `{value}`: {reason}"),
+            Self::MissingSensor {
+                selector: Some(selector),
+                available,
+            } => write!(
+                f,
+                "iio sensor `{selector}` not found, available sensors: {}\nhint: run `lilight \
+                 sensor list` to see the available sensors",
+                if available.is_empty() {
+                    "none".to_owned()
+                } else {
+                    available.join(", ")
+                }
+            ),
+            Self::MissingSensor { selector: None, .. } => {
+                write!(f, "no light sensor found in `/sys/bus/iio/devices`")
+            }
+            Self::Config { path, reason } => {
+                write!(f, "invalid config file `{}`: {reason}", path.display())
+            }
+            Self::Io {
+                path: Some(path),
+                source,
+            } => write!(f, "`{}`: {source}", path.display()),
+            Self::Io { path: None, source } => write!(f, "{source}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(source: io::Error) -> Self {
+        Self::Io { path: None, source }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use super::Error;
+
+    #[test]
+    fn io() {
+        let e = Error::io(
+            "/sys/class/backlight/x/brightness",
+            io::Error::from(io::ErrorKind::PermissionDenied),
+        );
+        assert!(matches!(e, Error::PermissionDenied { .. }));
+        assert!(e.to_string().contains("hint: "));
+        let e = Error::io("/x", io::Error::from(io::ErrorKind::NotFound));
+        assert!(matches!(e, Error::Io { path: Some(_), .. }));
+    }
+}
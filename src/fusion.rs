@@ -0,0 +1,67 @@
+//! Combining readings from several sensors.
+
+use serde::Deserialize;
+
+/// How readings from several sensors are combined into one illuminance.
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[non_exhaustive]
+pub enum Fusion {
+    /// Brightest reading, so covering one sensor doesn't dim the screen.
+    #[default]
+    Max,
+    /// Darkest reading.
+    Min,
+    /// Mean of the readings.
+    Mean,
+    /// Mean weighted by `weights`, in the same order as the sensors.
+    Weighted,
+    /// First sensor that could be read, in the configured order.
+    First,
+}
+
+impl Fusion {
+    /// Combine `readings` in sensor order, `None` for a sensor that couldn't be read. Only
+    /// returns `None` when no sensor could be read.
+    pub fn combine(self, readings: &[Option<f64>], weights: &[f64]) -> Option<f64> {
+        let mut available = readings
+            .iter()
+            .enumerate()
+            .filter_map(|(i, x)| x.map(|x| (x, weights.get(i).copied().unwrap_or(1.0))));
+        match self {
+            Self::Max => available.map(|(x, _)| x).reduce(f64::max),
+            Self::Min => available.map(|(x, _)| x).reduce(f64::min),
+            Self::Mean => {
+                let (sum, count) =
+                    available.fold((0.0, 0), |(sum, count), (x, _)| (sum + x, count + 1));
+                (count != 0).then(|| sum / count as f64)
+            }
+            Self::Weighted => {
+                let (sum, total) = available.fold((0.0, 0.0), |(sum, total), (x, weight)| {
+                    (sum + x * weight, total + weight)
+                });
+                (total != 0.0).then(|| sum / total)
+            }
+            Self::First => available.next().map(|(x, _)| x),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Fusion;
+
+    #[test]
+    fn combine() {
+        let readings = [Some(10.0), None, Some(30.0)];
+        assert_eq!(Fusion::Max.combine(&readings, &[]), Some(30.0));
+        assert_eq!(Fusion::Min.combine(&readings, &[]), Some(10.0));
+        assert_eq!(Fusion::Mean.combine(&readings, &[]), Some(20.0));
+        assert_eq!(
+            Fusion::Weighted.combine(&readings, &[3.0, 5.0, 1.0]),
+            Some(15.0)
+        );
+        assert_eq!(Fusion::First.combine(&[None, Some(5.0)], &[]), Some(5.0));
+        assert_eq!(Fusion::Mean.combine(&[None, None], &[]), None);
+    }
+}
@@ -0,0 +1,321 @@
+use std::{
+    fs::{self, File},
+    path::{Path, PathBuf},
+};
+
+use log::debug;
+
+use crate::{
+    device::{read_attribute, read_attribute_once},
+    error::{Error, Result},
+};
+
+/// An ambient light sensor in `/sys/bus/iio/devices`.
+#[derive(Debug)]
+pub struct Iio {
+    name: PathBuf,
+    /// Canonical sysfs path, e.g. `/sys/devices/.../HID-SENSOR-200041.2.auto/iio:device0`.
+    path: PathBuf,
+    device_name: Option<String>,
+    label: Option<String>,
+    /// `in_illuminance_raw`, or `in_illuminance_input` already in lux.
+    in_illuminance: File,
+    processed: bool,
+    in_illuminance_scale: f64,
+    in_illuminance_offset: f64,
+}
+
+impl Iio {
+    /// Open the sensor at `dir_path`, e.g. `/sys/bus/iio/devices/iio:device0`. Fails for iio
+    /// devices without an illuminance channel.
+    pub fn new<P>(dir_path: P) -> Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let dir_path = dir_path.as_ref();
+        let raw_path = dir_path.join("in_illuminance_raw");
+        let input_path = dir_path.join("in_illuminance_input");
+        let processed = !raw_path.exists() && input_path.exists();
+        let channel_path = if processed { input_path } else { raw_path };
+        // both are optional in the iio sysfs ABI
+        let optional = |file: &str, default: f64| {
+            let path = dir_path.join(file);
+            if path.exists() {
+                read_attribute_once(&path)
+            } else {
+                Ok(default)
+            }
+        };
+        Ok(Self {
+            name: dir_path
+                .strip_prefix("/sys/bus/iio/devices/")
+                .unwrap_or(dir_path)
+                .to_path_buf(),
+            path: fs::canonicalize(dir_path).map_err(|e| Error::io(dir_path, e))?,
+            device_name: fs::read_to_string(dir_path.join("name"))
+                .ok()
+                .map(|x| x.trim().to_owned()),
+            label: fs::read_to_string(dir_path.join("label"))
+                .ok()
+                .map(|x| x.trim().to_owned()),
+            in_illuminance: File::open(&channel_path).map_err(|e| Error::io(&channel_path, e))?,
+            processed,
+            in_illuminance_scale: optional("in_illuminance_scale", 1.0)?,
+            in_illuminance_offset: optional("in_illuminance_offset", 0.0)?,
+        })
+    }
+    /// Path relative to `/sys/bus/iio/devices`, e.g. `iio:device0`.
+    pub fn name(&self) -> &Path {
+        &self.name
+    }
+    /// Canonical sysfs path, e.g. `/sys/devices/.../HID-SENSOR-200041.2.auto/iio:device0`.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+    /// The `name` attribute, usually the driver.
+    pub fn device_name(&self) -> Option<&str> {
+        self.device_name.as_deref()
+    }
+    /// The `label` attribute, e.g. `lid`.
+    pub fn label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+    /// Lux per raw unit, 1 when the sensor has no `in_illuminance_scale`.
+    pub fn scale(&self) -> f64 {
+        self.in_illuminance_scale
+    }
+    /// Added to raw readings before scaling, 0 when the sensor has no `in_illuminance_offset`.
+    pub fn offset(&self) -> f64 {
+        self.in_illuminance_offset
+    }
+    /// Every light sensor in `/sys/bus/iio/devices`, and the errors from the iio devices that
+    /// couldn't be opened.
+    pub fn all() -> (Vec<Self>, Vec<Error>) {
+        match fs::read_dir("/sys/bus/iio/devices/") {
+            Ok(dir) => {
+                let mut iios = vec![];
+                let mut errors = vec![];
+                for iio in dir {
+                    match iio.map_err(Error::from).and_then(|x| Self::new(x.path())) {
+                        Ok(x) => iios.push(x),
+                        Err(e) => errors.push(e),
+                    }
+                }
+                (iios, errors)
+            }
+            Err(e) => (vec![], vec![Error::io("/sys/bus/iio/devices/", e)]),
+        }
+    }
+    /// The first light sensor that could be opened, and the errors from the iio devices before
+    /// it.
+    pub fn first() -> (Option<Self>, Vec<Error>) {
+        match fs::read_dir("/sys/bus/iio/devices/") {
+            Ok(dir) => {
+                let mut errors = vec![];
+                for iio in dir {
+                    match iio.map_err(Error::from).and_then(|x| Self::new(x.path())) {
+                        Ok(x) => return (Some(x), errors),
+                        Err(e) => errors.push(e),
+                    }
+                }
+                (None, errors)
+            }
+            Err(e) => (None, vec![Error::io("/sys/bus/iio/devices/", e)]),
+        }
+    }
+    /// Find a sensor by, in this order, `iio:deviceN`, its `label` or `name` attribute, or the
+    /// path of the underlying device: absolute (symlinks such as
+    /// `/sys/bus/iio/devices/iio:device0` are resolved), or its last components such as
+    /// `HID-SENSOR-200041.2.auto`.
+    pub fn find(selector: &str) -> Result<Self> {
+        let (mut iios, errors) = Self::all();
+        if let Some(i) = (0..iios.len())
+            .filter_map(|i| Some((iios[i].match_rank(selector)?, i)))
+            .min()
+            .map(|(_, i)| i)
+        {
+            return Ok(iios.swap_remove(i));
+        }
+        for e in errors {
+            debug!("{e}");
+        }
+        Err(Error::MissingSensor {
+            selector: Some(selector.to_owned()),
+            available: iios.iter().map(Self::describe).collect(),
+        })
+    }
+    /// Whether `selector` names this sensor, see [`Iio::find`].
+    pub fn matches(&self, selector: &str) -> bool {
+        self.match_rank(selector).is_some()
+    }
+    /// How `selector` names this sensor, lower ranks taking priority in [`Iio::find`].
+    fn match_rank(&self, selector: &str) -> Option<u8> {
+        let selector_path = Path::new(selector);
+        let device = self.path.parent()?;
+        if self.name == selector_path {
+            Some(0)
+        } else if self.label.as_deref() == Some(selector) {
+            Some(1)
+        } else if self.device_name.as_deref() == Some(selector) {
+            Some(2)
+        } else if selector_path.is_absolute() {
+            let selector_path =
+                fs::canonicalize(selector_path).unwrap_or_else(|_| selector_path.to_path_buf());
+            (self.path == selector_path || device == selector_path).then_some(3)
+        } else {
+            // only the underlying device's own components, not the buses above it
+            (!selector.is_empty() && device.ends_with(selector_path)).then_some(3)
+        }
+    }
+    /// Files the sensor's channels are read from, e.g. `in_illuminance_raw`.
+    pub fn channel_files(&self) -> Result<Vec<String>> {
+        let mut files = vec![];
+        for entry in fs::read_dir(&self.path).map_err(|e| Error::io(&self.path, e))? {
+            let file_name = entry?.file_name();
+            if let Some(file_name) = file_name.to_str()
+                && file_name.starts_with("in_")
+                && (file_name.ends_with("_raw") || file_name.ends_with("_input"))
+            {
+                files.push(file_name.to_owned());
+            }
+        }
+        files.sort();
+        Ok(files)
+    }
+    /// Channels exposed by the sensor, e.g. `illuminance` for `in_illuminance_raw`.
+    pub fn channels(&self) -> Result<Vec<String>> {
+        let mut channels = self
+            .channel_files()?
+            .iter()
+            .filter_map(|x| {
+                let x = x.strip_prefix("in_")?;
+                x.strip_suffix("_raw").or_else(|| x.strip_suffix("_input"))
+            })
+            .map(str::to_owned)
+            .collect::<Vec<_>>();
+        channels.sort();
+        channels.dedup();
+        Ok(channels)
+    }
+    /// `iio:deviceN` followed by the `name` and `label` attributes when present.
+    pub fn describe(&self) -> String {
+        let mut description = self.name.display().to_string();
+        if let Some(device_name) = &self.device_name {
+            description += &format!(" (name: {device_name})");
+        }
+        if let Some(label) = &self.label {
+            description += &format!(" (label: {label})");
+        }
+        description
+    }
+    /// Illuminance in lux, following the iio sysfs ABI: `in_illuminance_input`, or
+    /// `(raw + offset) * scale`.
+    pub fn get_illuminance(&mut self) -> Result<f64> {
+        let file = if self.processed {
+            "in_illuminance_input"
+        } else {
+            "in_illuminance_raw"
+        };
+        let value: f64 = read_attribute(&mut self.in_illuminance, &self.path.join(file))?;
+        if self.processed {
+            return Ok(value);
+        }
+        Ok((value + self.in_illuminance_offset) * self.in_illuminance_scale)
+    }
+}
+
+/// Find the sensor matching `sensor`, or the first one found.
+pub fn open_iio(sensor: Option<String>) -> Result<Iio> {
+    match sensor {
+        Some(x) => Iio::find(&x),
+        None => {
+            let (iio, errors) = Iio::first();
+            for e in errors {
+                debug!("error while getting first iio: {e}");
+            }
+            iio.ok_or(Error::MissingSensor {
+                selector: None,
+                available: vec![],
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        fs::{self, File},
+        os::unix,
+    };
+
+    use super::Iio;
+    use crate::temp_dir::TempDir;
+
+    fn iio(name: &str, path: &str, device_name: Option<&str>, label: Option<&str>) -> Iio {
+        Iio {
+            name: name.into(),
+            path: path.into(),
+            device_name: device_name.map(str::to_owned),
+            label: label.map(str::to_owned),
+            in_illuminance: File::open("/dev/null").unwrap(),
+            processed: false,
+            in_illuminance_scale: 1.0,
+            in_illuminance_offset: 0.0,
+        }
+    }
+
+    #[test]
+    fn selectors() {
+        let iio = iio(
+            "iio:device0",
+            "/sys/devices/platform/HID-SENSOR-200041.2.auto/iio:device0",
+            Some("als"),
+            Some("lid"),
+        );
+        for selector in [
+            "iio:device0",
+            "als",
+            "lid",
+            "HID-SENSOR-200041.2.auto",
+            "platform/HID-SENSOR-200041.2.auto",
+            "/sys/devices/platform/HID-SENSOR-200041.2.auto",
+        ] {
+            assert!(iio.matches(selector), "{selector}");
+        }
+        for selector in ["devices", "platform", "", "iio:device1", "/sys/devices"] {
+            assert!(!iio.matches(selector), "{selector}");
+        }
+        // a label wins over a name or a device
+        assert!(iio.match_rank("lid") < iio.match_rank("als"));
+        assert!(iio.match_rank("als") < iio.match_rank("HID-SENSOR-200041.2.auto"));
+    }
+
+    #[test]
+    fn symlinked_selector() {
+        let dir = TempDir::new("iio-link");
+        let device = dir.join("device/iio:device0");
+        fs::create_dir_all(&device).unwrap();
+        let link = dir.join("iio:device0");
+        unix::fs::symlink(&device, &link).unwrap();
+        let iio = iio(
+            "iio:device0",
+            device.canonicalize().unwrap().to_str().unwrap(),
+            None,
+            None,
+        );
+        assert!(iio.matches(link.to_str().unwrap()));
+        assert!(!iio.matches(dir.to_str().unwrap()));
+    }
+
+    #[test]
+    fn optional_attributes() {
+        let dir = TempDir::new("iio");
+        fs::write(dir.join("in_illuminance_raw"), "40\n").unwrap();
+        fs::write(dir.join("in_illuminance_scale"), "0.5\n").unwrap();
+        assert_eq!(Iio::new(&dir).unwrap().get_illuminance().unwrap(), 20.0);
+
+        fs::remove_file(dir.join("in_illuminance_raw")).unwrap();
+        fs::write(dir.join("in_illuminance_input"), "123.5\n").unwrap();
+        assert_eq!(Iio::new(&dir).unwrap().get_illuminance().unwrap(), 123.5);
+    }
+}
@@ -0,0 +1,111 @@
+use std::{
+    ffi::{CString, OsString},
+    io, mem,
+    os::{
+        fd::{AsFd, AsRawFd, BorrowedFd, FromRawFd, OwnedFd},
+        unix::ffi::{OsStrExt, OsStringExt},
+    },
+    path::Path,
+};
+
+/// Watches a single file through its parent directory, so the file being replaced by an editor
+/// (written to a temporary file and renamed over) is noticed too.
+pub struct Inotify {
+    fd: OwnedFd,
+    file_name: OsString,
+}
+
+impl Inotify {
+    pub fn watch(path: &Path) -> io::Result<Self> {
+        let file_name = path
+            .file_name()
+            .ok_or(io::Error::from(io::ErrorKind::InvalidInput))?
+            .to_owned();
+        let dir = match path.parent() {
+            Some(x) if !x.as_os_str().is_empty() => x,
+            _ => Path::new("."),
+        };
+        let dir = CString::new(dir.as_os_str().as_bytes()).map_err(io::Error::other)?;
+
+        let fd = unsafe { libc::inotify_init1(libc::IN_NONBLOCK | libc::IN_CLOEXEC) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+        if unsafe {
+            libc::inotify_add_watch(
+                fd.as_raw_fd(),
+                dir.as_ptr(),
+                libc::IN_CLOSE_WRITE | libc::IN_MOVED_TO,
+            )
+        } < 0
+        {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Self { fd, file_name })
+    }
+    /// Whether the file was written or replaced since the last call, without blocking.
+    pub fn changed(&self) -> io::Result<bool> {
+        let mut changed = false;
+        let mut buf = [0u8; 4096];
+        loop {
+            let n = unsafe {
+                libc::read(
+                    self.fd.as_raw_fd(),
+                    buf.as_mut_ptr() as *mut libc::c_void,
+                    buf.len(),
+                )
+            };
+            if n < 0 {
+                let e = io::Error::last_os_error();
+                return match e.kind() {
+                    io::ErrorKind::WouldBlock => Ok(changed),
+                    _ => Err(e),
+                };
+            }
+            let mut offset = 0;
+            while offset < n as usize {
+                let event: libc::inotify_event = unsafe {
+                    (buf.as_ptr().add(offset) as *const libc::inotify_event).read_unaligned()
+                };
+                let name_start = offset + mem::size_of::<libc::inotify_event>();
+                let name = &buf[name_start..name_start + event.len as usize];
+                let name = name.split(|x| *x == 0).next().unwrap_or_default();
+                changed |= OsString::from_vec(name.to_vec()) == self.file_name;
+                offset = name_start + event.len as usize;
+            }
+        }
+    }
+}
+
+impl AsFd for Inotify {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.fd.as_fd()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process};
+
+    use super::Inotify;
+
+    #[test]
+    fn changed() {
+        let dir = env::temp_dir().join(format!("lilight-inotify-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("lilight.toml");
+        let inotify = Inotify::watch(&path).unwrap();
+        assert!(!inotify.changed().unwrap());
+
+        fs::write(dir.join("other.toml"), "").unwrap();
+        assert!(!inotify.changed().unwrap());
+        fs::write(dir.join("lilight.toml.tmp"), "").unwrap();
+        fs::rename(dir.join("lilight.toml.tmp"), &path).unwrap();
+        assert!(inotify.changed().unwrap());
+        fs::write(&path, "").unwrap();
+        assert!(inotify.changed().unwrap());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,105 @@
+//! The daemon's control socket.
+
+use std::{
+    env, fs,
+    io::{self, BufRead, BufReader, Write},
+    os::{
+        fd::{AsFd, BorrowedFd},
+        unix::net::{UnixListener, UnixStream},
+    },
+    path::PathBuf,
+    time::Duration,
+};
+
+use log::warn;
+
+use crate::systemd;
+
+/// `$XDG_RUNTIME_DIR/lilight.sock`, where the daemon listens for commands.
+pub fn socket_path() -> io::Result<PathBuf> {
+    env::var_os("XDG_RUNTIME_DIR")
+        .map(|x| PathBuf::from(x).join("lilight.sock"))
+        .ok_or(io::Error::new(
+            io::ErrorKind::NotFound,
+            "XDG_RUNTIME_DIR is not set",
+        ))
+}
+
+/// The daemon's control socket, taking one command per connection (`status`, `pause`,
+/// `resume`, `toggle` or `reload`) and answering with one line.
+pub struct Ipc {
+    listener: UnixListener,
+    /// Path to remove on drop, unless the socket came from socket activation.
+    bound: Option<PathBuf>,
+}
+
+impl Ipc {
+    /// Use the socket passed by socket activation, or bind [`socket_path`].
+    pub fn new() -> io::Result<Self> {
+        let (listener, bound) = match systemd::listen_fds().into_iter().next() {
+            Some(fd) => (UnixListener::from(fd), None),
+            None => {
+                let path = socket_path()?;
+                match fs::remove_file(&path) {
+                    Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+                    _ => {}
+                }
+                (UnixListener::bind(&path)?, Some(path))
+            }
+        };
+        listener.set_nonblocking(true)?;
+        Ok(Self { listener, bound })
+    }
+    /// Requests waiting to be accepted, without blocking.
+    pub fn accept(&self) -> io::Result<Vec<Request>> {
+        let mut requests = vec![];
+        loop {
+            let stream = match self.listener.accept() {
+                Ok((stream, _)) => stream,
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(requests),
+                Err(e) => return Err(e),
+            };
+            stream.set_nonblocking(false)?;
+            stream.set_read_timeout(Some(Duration::from_millis(100)))?;
+            let mut command = String::new();
+            if let Err(e) = BufReader::new(&stream).read_line(&mut command) {
+                warn!("error while reading an ipc request: {e}");
+                continue;
+            }
+            requests.push(Request {
+                stream,
+                command: command.trim().to_owned(),
+            });
+        }
+    }
+}
+
+impl AsFd for Ipc {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.listener.as_fd()
+    }
+}
+
+impl Drop for Ipc {
+    fn drop(&mut self) {
+        if let Some(path) = &self.bound {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+/// A command received on the [`Ipc`] socket.
+pub struct Request {
+    stream: UnixStream,
+    /// The command, without the trailing newline.
+    pub command: String,
+}
+
+impl Request {
+    /// Answer with one line and close the connection.
+    pub fn reply(mut self, reply: &str) {
+        if let Err(e) = writeln!(self.stream, "{reply}") {
+            warn!("error while replying to an ipc request: {e}");
+        }
+    }
+}
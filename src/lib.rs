@@ -0,0 +1,47 @@
+//! Control screen and keyboard brightness on Linux through sysfs, optionally following an
+//! ambient light sensor.
+//!
+//! ```no_run
+//! use lilight::{Limits, Value, open_device, set_brightness, transition::TransitionSetting};
+//!
+//! let mut device = open_device(None)?;
+//! let value = "+10%".parse::<Value>().unwrap();
+//! set_brightness(&mut device, value, &TransitionSetting::default(), &Limits::default())?;
+//! # Ok::<(), lilight::Error>(())
+//! ```
+//!
+//! [`daemon::run`] drives devices from sensors the way `lilight daemon` does.
+//!
+//! Structs with public fields may gain fields in minor releases. Build them with their `new`
+//! function or [`Default`], then change the fields from there.
+
+#![warn(missing_docs)]
+
+#[cfg(feature = "async")]
+pub mod asynchronous;
+pub mod config;
+pub mod curve;
+pub mod daemon;
+mod device;
+mod error;
+pub mod fusion;
+mod iio;
+mod inotify;
+pub mod ipc;
+pub mod lock;
+mod poll;
+mod signal;
+pub mod systemd;
+#[cfg(test)]
+mod temp_dir;
+pub mod transition;
+pub mod udev;
+mod uevent;
+mod value;
+
+pub use crate::{
+    device::{Device, Limits, open_device, set_brightness, target_brightness},
+    error::{Error, Result},
+    iio::{Iio, open_iio},
+    value::{Prefix, Type, Value, to_value},
+};
@@ -0,0 +1,146 @@
+//! The per-user daemon lock.
+
+use std::{
+    env,
+    fs::File,
+    io::{self, Read, Seek, Write},
+    os::{
+        fd::AsRawFd,
+        unix::fs::{MetadataExt, OpenOptionsExt},
+    },
+    path::PathBuf,
+    process, thread,
+    time::{Duration, Instant},
+};
+
+use log::info;
+
+/// How long `--replace` waits for the running daemon to exit.
+const REPLACE_TIMEOUT: Duration = Duration::from_secs(5);
+/// How long `--replace` waits for a daemon that just started to write its pid.
+const PID_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// `$XDG_RUNTIME_DIR/lilight.lock`, or a per-user file in the temporary directory.
+pub fn lock_path() -> PathBuf {
+    match env::var_os("XDG_RUNTIME_DIR") {
+        Some(x) => PathBuf::from(x).join("lilight.lock"),
+        None => env::temp_dir().join(format!("lilight-{}.lock", unsafe { libc::getuid() })),
+    }
+}
+
+/// Open the lock file without following symlinks and check it belongs to the current user, as
+/// anyone can create it when it's in the temporary directory.
+fn open(create: bool) -> io::Result<File> {
+    let path = lock_path();
+    let file = File::options()
+        .read(true)
+        .write(create)
+        .create(create)
+        .truncate(false)
+        .custom_flags(libc::O_NOFOLLOW)
+        .open(&path)
+        .map_err(|e| io::Error::new(e.kind(), format!("`{}`: {e}", path.display())))?;
+    if file.metadata()?.uid() != unsafe { libc::getuid() } {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("`{}` belongs to another user", path.display()),
+        ));
+    }
+    Ok(file)
+}
+
+/// The pid written in the lock file, if any.
+fn read_pid(mut file: &File) -> io::Result<Option<libc::pid_t>> {
+    let mut buf = String::new();
+    file.rewind()?;
+    file.read_to_string(&mut buf)?;
+    Ok(buf.trim().parse().ok())
+}
+
+fn try_lock(file: &File) -> io::Result<bool> {
+    if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } == 0 {
+        return Ok(true);
+    }
+    let e = io::Error::last_os_error();
+    match e.kind() {
+        io::ErrorKind::WouldBlock => Ok(false),
+        _ => Err(e),
+    }
+}
+
+/// Pid of the running daemon, `Some(None)` if one runs but its pid is unknown.
+pub fn running_pid() -> io::Result<Option<Option<libc::pid_t>>> {
+    let file = match open(false) {
+        Ok(x) => x,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    // taking the lock releases it right away when `file` is dropped
+    if try_lock(&file)? {
+        return Ok(None);
+    }
+    Ok(Some(read_pid(&file)?))
+}
+
+/// Makes sure only one daemon runs per user. The lock is released when the process exits.
+pub struct Lock {
+    _file: File,
+}
+
+impl Lock {
+    /// Take the lock, or with `replace` ask the running daemon to exit with SIGTERM and take
+    /// the lock once it has.
+    pub fn acquire(replace: bool) -> io::Result<Self> {
+        let mut file = open(true)?;
+        if !try_lock(&file)? {
+            let mut pid = read_pid(&file)?;
+            // a daemon that just took the lock may not have written its pid yet
+            let start = Instant::now();
+            while replace && pid.is_none() && start.elapsed() < PID_TIMEOUT {
+                thread::sleep(Duration::from_millis(50));
+                pid = read_pid(&file)?;
+            }
+            let pid_message = pid.map(|x| format!(" (pid {x})")).unwrap_or_default();
+            let pid = match pid {
+                Some(pid) if replace => pid,
+                None if replace => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::AlreadyExists,
+                        format!(
+                            "lilight daemon is already running but its pid isn't in `{}`, \
+                             stop it yourself",
+                            lock_path().display()
+                        ),
+                    ));
+                }
+                _ => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::AlreadyExists,
+                        format!(
+                            "lilight daemon is already running{pid_message}, use `--replace` to \
+                             take over"
+                        ),
+                    ));
+                }
+            };
+            info!("asking the running daemon{pid_message} to exit");
+            if unsafe { libc::kill(pid, libc::SIGTERM) } != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let start = Instant::now();
+            while !try_lock(&file)? {
+                if start.elapsed() > REPLACE_TIMEOUT {
+                    return Err(io::Error::new(
+                        io::ErrorKind::TimedOut,
+                        format!("the running daemon{pid_message} didn't exit"),
+                    ));
+                }
+                thread::sleep(Duration::from_millis(50));
+            }
+        }
+        file.set_len(0)?;
+        file.rewind()?;
+        write!(file, "{}", process::id())?;
+        Ok(Self { _file: file })
+    }
+}
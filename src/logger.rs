@@ -0,0 +1,81 @@
+use std::{
+    env,
+    fs::File,
+    io::{self, Write},
+    os::{fd::AsFd, unix::fs::MetadataExt},
+};
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+/// Writes records to stderr, prefixed with their syslog priority (see `sd-daemon(3)`) when
+/// stderr is connected to the journal, so journald keeps the levels.
+struct Logger {
+    journald: bool,
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let mut stderr = io::stderr().lock();
+        let _ = if self.journald {
+            let priority = match record.level() {
+                Level::Error => 3,
+                Level::Warn => 4,
+                Level::Info => 6,
+                Level::Debug | Level::Trace => 7,
+            };
+            writeln!(stderr, "<{priority}>{}", record.args())
+        } else {
+            let level = record.level().as_str().to_lowercase();
+            writeln!(stderr, "{level}: {}", record.args())
+        };
+    }
+    fn flush(&self) {}
+}
+
+/// Install the logger. Until [`set_level`] is called only errors and warnings are shown.
+pub fn init() {
+    let logger = Logger {
+        journald: stderr_is_journal(),
+    };
+    if log::set_logger(Box::leak(Box::new(logger))).is_ok() {
+        log::set_max_level(LevelFilter::Warn);
+    }
+}
+
+/// Whether stderr is the stream `$JOURNAL_STREAM` names as `device:inode`, which a child of the
+/// service may inherit after redirecting stderr elsewhere.
+fn stderr_is_journal() -> bool {
+    let Ok(stream) = env::var("JOURNAL_STREAM") else {
+        return false;
+    };
+    let Some(metadata) = io::stderr()
+        .as_fd()
+        .try_clone_to_owned()
+        .and_then(|fd| File::from(fd).metadata())
+        .ok()
+    else {
+        return false;
+    };
+    stream == format!("{}:{}", metadata.dev(), metadata.ino())
+}
+
+/// Set the level from, in order of precedence, `$LILIGHT_LOG`, the config file and `info`,
+/// then raise it once per `-v` and lower it once per `-q`.
+pub fn set_level(config: Option<LevelFilter>, verbose: u8, quiet: u8) {
+    let base = env::var("LILIGHT_LOG")
+        .ok()
+        .and_then(|x| x.parse().ok())
+        .or(config)
+        .unwrap_or(LevelFilter::Info);
+    let levels = LevelFilter::iter().collect::<Vec<_>>();
+    let index = (base as usize + verbose as usize)
+        .saturating_sub(quiet as usize)
+        .min(levels.len() - 1);
+    log::set_max_level(levels[index]);
+}
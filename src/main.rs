@@ -1,485 +1,500 @@
-use std::{
-    borrow::Cow,
-    env,
-    fs::{self, File},
-    io::{self, Read, Seek, Write},
-    path::{Path, PathBuf},
-    thread,
-    time::Duration,
-};
+use std::{env, io, path::PathBuf, process::ExitCode, thread, time::Duration};
 
 use clap::Parser;
+use lilight::{
+    Device, Error, Iio, Limits, Result, Value,
+    config::{self, Config},
+    curve::Unit,
+    daemon::{self, DaemonSetting, DeviceSetting},
+    fusion::Fusion,
+    open_device, open_iio, set_brightness, systemd,
+    transition::TransitionSetting,
+    udev,
+};
+use log::{debug, error, trace, warn};
 
 use crate::{
-    cli::{Cli, Command, Prefix, Type, Value},
-    config::Config,
-    curve::Curve,
+    cli::{Cli, Command, Format, GenerateCommand, PowerState, SensorCommand},
+    compat::Tool,
 };
 
+mod calibrate;
 mod cli;
-mod config;
-mod curve;
-
-#[derive(Debug)]
-struct Device {
-    name: PathBuf,
-    brightness: File,
-    max_brightness: File, // TODO: maybe just store the value?
-}
-
-impl Device {
-    fn new<P>(dir_path: P) -> io::Result<Self>
-    where
-        P: AsRef<Path>,
-    {
-        Ok(Self {
-            name: dir_path
-                .as_ref()
-                .strip_prefix("/sys/class/")
-                .unwrap_or(dir_path.as_ref())
-                .to_path_buf(),
-            brightness: File::options()
-                .read(true)
-                .write(true)
-                .open(dir_path.as_ref().join("brightness"))?,
-            max_brightness: File::open(dir_path.as_ref().join("max_brightness"))?,
-        })
-    }
-    fn all() -> (Vec<Self>, Vec<io::Error>) {
-        [
-            fs::read_dir("/sys/class/backlight"),
-            fs::read_dir("/sys/class/leds"),
-        ]
-        .into_iter()
-        .fold((vec![], vec![]), |(mut devices, mut errors), dir| {
-            match dir {
-                Ok(dir) => {
-                    for device in dir {
-                        match device.and_then(|x| Self::new(x.path())) {
-                            Ok(x) => devices.push(x),
-                            Err(e) => errors.push(e),
-                        }
-                    }
-                }
-                Err(e) => errors.push(e),
-            };
-            (devices, errors)
-        })
-    }
-    fn first() -> (Option<Self>, Vec<io::Error>) {
-        let mut errors = vec![];
-        for dir in [
-            fs::read_dir("/sys/class/backlight"),
-            fs::read_dir("/sys/class/leds"),
-        ] {
-            match dir {
-                Ok(dir) => {
-                    for device in dir {
-                        match device.and_then(|x| Self::new(x.path())) {
-                            Ok(x) => return (Some(x), errors),
-                            Err(e) => errors.push(e),
-                        }
-                    }
-                }
-                Err(e) => errors.push(e),
-            }
-        }
-        (None, errors)
-    }
-    fn get_brightness(&mut self) -> io::Result<i32> {
-        self.brightness.rewind()?;
-        let mut buf = String::new();
-        let _ = self.brightness.read_to_string(&mut buf)?;
-        buf.trim().parse().map_err(|e| io::Error::other(e))
-    }
-    fn set_brightness(&mut self, new_brightness: i32) -> io::Result<()> {
-        self.brightness
-            .write_all(new_brightness.max(0).to_string().as_bytes())
-    }
-    fn get_max_brightness(&mut self) -> io::Result<i32> {
-        self.max_brightness.rewind()?;
-        let mut buf = String::new();
-        let _ = self.max_brightness.read_to_string(&mut buf)?;
-        buf.trim().parse().map_err(|e| io::Error::other(e))
-    }
-}
-
-#[derive(Debug)]
-struct Iio {
-    name: PathBuf,
-    in_illuminance_raw: File,
-    in_illuminance_scale: f32,  // TODO: check type
-    in_illuminance_offset: i32, // TODO: check type
-}
-
-impl Iio {
-    fn new<P>(dir_path: P) -> io::Result<Self>
-    where
-        P: AsRef<Path>,
-    {
-        Ok(Self {
-            name: dir_path
-                .as_ref()
-                .strip_prefix("/sys/bus/iio/devices/")
-                .unwrap_or(dir_path.as_ref())
-                .to_path_buf(),
-            in_illuminance_raw: File::open(dir_path.as_ref().join("in_illuminance_raw"))?,
-            in_illuminance_scale: fs::read_to_string(
-                dir_path.as_ref().join("in_illuminance_scale"),
-            )?
-            .trim()
-            .parse()
-            .map_err(|e| io::Error::other(e))?,
-            in_illuminance_offset: fs::read_to_string(
-                dir_path.as_ref().join("in_illuminance_offset"),
-            )?
-            .trim()
-            .parse()
-            .map_err(|e| io::Error::other(e))?,
-        })
-    }
-    fn all() -> (Vec<Self>, Vec<io::Error>) {
-        match fs::read_dir("/sys/bus/iio/devices/") {
-            Ok(dir) => {
-                let mut iios = vec![];
-                let mut errors = vec![];
-                for iio in dir {
-                    match iio.and_then(|x| Self::new(x.path())) {
-                        Ok(x) => iios.push(x),
-                        Err(e) => errors.push(e),
-                    }
-                }
-                (iios, errors)
-            }
-            Err(e) => (vec![], vec![e]),
-        }
-    }
-    fn first() -> (Option<Self>, Vec<io::Error>) {
-        match fs::read_dir("/sys/bus/iio/devices/") {
-            Ok(dir) => {
-                let mut errors = vec![];
-                for iio in dir {
-                    match iio.and_then(|x| Self::new(x.path())) {
-                        Ok(x) => return (Some(x), errors),
-                        Err(e) => errors.push(e),
-                    }
-                }
-                (None, errors)
-            }
-            Err(e) => (None, vec![e]),
-        }
-    }
-    // TODO: check type
-    fn get_illuminance(&mut self) -> io::Result<i32> {
-        self.in_illuminance_raw.rewind()?;
-        let mut buf = String::new();
-        let _ = self.in_illuminance_raw.read_to_string(&mut buf)?;
-        match buf.trim().parse::<i32>() {
-            Ok(raw) => Ok(if self.in_illuminance_scale.fract() == 0.0 {
-                (raw + self.in_illuminance_offset) * self.in_illuminance_scale as i32
-            } else {
-                ((raw + self.in_illuminance_offset) as f32 * self.in_illuminance_scale) as i32
-            }),
-            Err(e) => Err(io::Error::other(e)),
-        }
-    }
-}
+mod compat;
+mod doctor;
+mod logger;
+#[cfg(test)]
+#[path = "temp_dir.rs"]
+mod temp_dir;
 
 #[derive(Debug)]
 enum Setting {
     Set {
         value: Value,
         device: Option<PathBuf>,
-        transition_enable: bool,
-        transition_time: u64,
-        transition_step: u64,
+        transition: TransitionSetting,
+        limits: Limits,
     },
     Get {
         max: bool,
+        actual: bool,
+        format: Option<Format>,
         device: Option<PathBuf>,
         all: bool,
     },
+    Power {
+        state: PowerState,
+        device: Option<PathBuf>,
+    },
     List,
-    Daemon {
+    SensorList,
+    SensorRead {
+        /// Sensors whose readings are combined, the first one found when empty.
+        sensors: Vec<String>,
+        fusion: Fusion,
+        weights: Vec<f64>,
+        follow: bool,
+    },
+    Calibrate {
         device: Option<PathBuf>,
-        transition_enable: bool,
-        transition_time: u64,
-        transition_step: u64,
-        iio_sensor: Option<PathBuf>,
-        curve: Curve,
+        iio_sensor: Option<String>,
+        unit: Unit,
+        /// Whether the curve comes from `[device."<name>"]` rather than `[iio]`.
+        device_curve: bool,
+    },
+    Daemon(DaemonSetting),
+    SystemdUnit {
+        socket: bool,
+    },
+    UdevRule {
+        group: String,
+        /// Where to install the rule, `None` to print it.
+        destination: Option<PathBuf>,
+    },
+    Doctor {
+        json: bool,
     },
 }
 
 impl Setting {
+    /// Resolve the command line against the config file. For each setting the command line
+    /// flag wins, then the `[device."<name>"]` table of the selected device, then the global
+    /// value. Device overrides only apply when the device is named by `--device` or
+    /// `default_device`.
     fn new(cli: Cli, config: Config) -> Self {
+        let device_config = |device: Option<PathBuf>| {
+            let device = device.or(config.default_device.clone());
+            let device_config = device
+                .as_ref()
+                .and_then(|x| config.device.get(x))
+                .cloned()
+                .unwrap_or_default();
+            (device, device_config)
+        };
+        let transition = |device_config: &config::Device, time: Option<u64>, step: Option<u64>| {
+            let mut transition = TransitionSetting::new(
+                time.or(device_config.transition.time)
+                    .unwrap_or(config.transition.time),
+                step.or(device_config.transition.step)
+                    .unwrap_or(config.transition.step),
+            );
+            transition.enable = device_config
+                .transition
+                .enable
+                .unwrap_or(config.transition.enable);
+            transition
+        };
+        // sensors combined by the daemon and `sensor read`
+        let sensors = |iio: Vec<String>, device_config: &config::Device| {
+            if !iio.is_empty() {
+                iio
+            } else if !device_config.sensors.is_empty() {
+                device_config.sensors.clone()
+            } else if let Some(sensor) = &device_config.sensor {
+                vec![sensor.clone()]
+            } else if !config.iio.sensors.is_empty() {
+                config.iio.sensors.clone()
+            } else {
+                config.iio.default_sensor.iter().cloned().collect()
+            }
+        };
+        let weights = |device_config: &config::Device| {
+            if !device_config.weights.is_empty() {
+                device_config.weights.clone()
+            } else {
+                config.iio.weights.clone()
+            }
+        };
         match cli.command {
             Command::Set {
                 value,
                 device,
                 transition_time,
                 transition_step,
-            } => Self::Set {
-                value,
-                device: device.or(config.default_device),
-                transition_enable: config.transition.enable,
-                transition_time: transition_time.unwrap_or(config.transition.time),
-                transition_step: transition_step.unwrap_or(config.transition.step),
-            },
-            Command::Get { max, device, all } => Self::Get {
+            } => {
+                let (device, device_config) = device_config(device);
+                Self::Set {
+                    value,
+                    device,
+                    transition: transition(&device_config, transition_time, transition_step),
+                    limits: Limits::new(device_config.min, device_config.max),
+                }
+            }
+            Command::Get {
                 max,
+                actual,
+                format,
+                device,
+                all,
+            } => Self::Get {
+                max,
+                actual,
+                format,
                 device: device.or(config.default_device),
                 all,
             },
+            Command::Power { state, device } => Self::Power {
+                state,
+                device: device.or(config.default_device),
+            },
             Command::List => Self::List,
+            Command::Sensor {
+                command: SensorCommand::List,
+            } => Self::SensorList,
+            Command::Sensor {
+                command: SensorCommand::Read { sensor, follow },
+            } => {
+                let (_, device_config) = device_config(None);
+                Self::SensorRead {
+                    sensors: sensors(sensor.into_iter().collect(), &device_config),
+                    fusion: device_config.fusion.unwrap_or(config.iio.fusion),
+                    weights: weights(&device_config),
+                    follow,
+                }
+            }
+            Command::Calibrate { device, iio } => {
+                let (device, device_config) = device_config(device);
+                Self::Calibrate {
+                    device,
+                    iio_sensor: iio.or(device_config.sensor).or(config.iio.default_sensor),
+                    unit: device_config
+                        .curve
+                        .as_ref()
+                        .unwrap_or(&config.iio.curve)
+                        .unit,
+                    device_curve: device_config.curve.is_some(),
+                }
+            }
+            Command::Generate {
+                command: GenerateCommand::SystemdUnit { socket },
+            } => Self::SystemdUnit { socket },
+            Command::UdevRule {
+                group,
+                install,
+                destination,
+            } => Self::UdevRule {
+                group,
+                destination: install.then_some(destination),
+            },
+            Command::Doctor { json } => Self::Doctor { json },
+            Command::Compat { .. } => unreachable!("translated in `main`"),
             Command::Daemon {
                 device,
                 transition_time,
                 transition_step,
                 iio,
-            } => Self::Daemon {
-                device: device.or(config.default_device),
-                transition_enable: config.transition.enable,
-                transition_time: transition_time.unwrap_or(config.transition.time),
-                transition_step: transition_step.unwrap_or(config.transition.step),
-                iio_sensor: iio.or(config.iio.default_sensor),
-                curve: config.iio.curve,
-            },
+                interval,
+                restore,
+                replace,
+            } => {
+                let devices = if !device.is_empty() {
+                    device.into_iter().map(Some).collect()
+                } else if !config.daemon.devices.is_empty() {
+                    config.daemon.devices.iter().cloned().map(Some).collect()
+                } else {
+                    vec![None]
+                };
+                let mut setting = DaemonSetting::new(
+                    devices
+                        .into_iter()
+                        .map(|device| {
+                            let (device, device_config) = device_config(device);
+                            let mut setting = DeviceSetting::new(
+                                device,
+                                device_config
+                                    .curve
+                                    .clone()
+                                    .unwrap_or(config.iio.curve.clone()),
+                            );
+                            setting.transition =
+                                transition(&device_config, transition_time, transition_step);
+                            setting.iio_sensors = sensors(iio.clone(), &device_config);
+                            setting.fusion = device_config.fusion.unwrap_or(config.iio.fusion);
+                            setting.weights = weights(&device_config);
+                            setting.limits = Limits::new(device_config.min, device_config.max);
+                            setting
+                        })
+                        .collect(),
+                );
+                setting.interval = interval.unwrap_or(config.daemon.interval);
+                setting.restore_on_exit = restore || config.daemon.restore_on_exit;
+                setting.replace = replace;
+                Self::Daemon(setting)
+            }
         }
     }
 }
 
-fn to_percentage(max: i32, value: i32) -> i32 {
-    (value * 100 + max / 2) / max
-}
-
-fn to_value(max: i32, percentage: i32) -> i32 {
-    (percentage * max + 100 / 2) / 100
-}
-
-fn set_brightness(
-    device: &mut Device,
-    Value {
-        prefix,
-        r#type,
-        num,
-    }: Value,
-    transition_enable: bool,
-    transition_time: u64,
-    transition_step: u64,
-) -> io::Result<()> {
-    let max_brightness = device.get_max_brightness()?;
-    let current_brightness = device.get_brightness()?;
-    let new_brightness = match prefix {
-        Prefix::None => match r#type {
-            Type::Number => num,
-            Type::Percentage => to_value(max_brightness, num),
+fn main() -> ExitCode {
+    logger::init();
+    let program = env::args_os().next().unwrap_or_default();
+    let cli = match Tool::from_program(&program) {
+        Some(tool) => tool.translate(
+            &env::args().skip(1).collect::<Vec<_>>(),
+            Cli {
+                command: Command::List,
+                config: None,
+                verbose: 0,
+                quiet: 0,
+            },
+        ),
+        None => match Cli::parse() {
+            Cli {
+                command: Command::Compat { tool, args },
+                config,
+                verbose,
+                quiet,
+            } => tool.translate(
+                &args,
+                Cli {
+                    command: Command::List,
+                    config,
+                    verbose,
+                    quiet,
+                },
+            ),
+            cli => Ok(cli),
         },
-        Prefix::Plus | Prefix::Minus => {
-            let sign = match prefix {
-                Prefix::Plus => 1,
-                Prefix::Minus => -1,
-                Prefix::None => unreachable!(),
-            };
-            match r#type {
-                Type::Number => current_brightness + sign * num,
-                Type::Percentage => to_value(
-                    max_brightness,
-                    to_percentage(max_brightness, current_brightness) + sign * num,
-                ),
-            }
+    };
+    let cli = match cli {
+        Ok(cli) => cli,
+        Err(e) => {
+            error!("{e}");
+            // like clap's usage errors
+            return ExitCode::from(2);
         }
-    }
-    .min(max_brightness);
+    };
+    logger::set_level(None, cli.verbose, cli.quiet);
+    trace!("{cli:#?}");
 
-    if transition_enable && transition_time != 0 && transition_step != 0 {
-        let steps = transition_time.div_ceil(transition_step);
-        let brightness_delta = new_brightness - current_brightness;
-        for i in 1..steps {
-            let new_brightness = current_brightness + brightness_delta * i as i32 / steps as i32;
-            device.set_brightness(new_brightness)?;
-            thread::sleep(Duration::from_millis(
-                transition_step.min(transition_time - transition_step * i),
-            ));
+    match run(cli) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            error!("{e}");
+            e.exit_code()
         }
     }
-
-    device.set_brightness(new_brightness)?;
-    Ok(())
 }
 
-fn main() -> io::Result<()> {
-    let cli = Cli::parse();
-    dbg!(&cli);
-
+fn run(cli: Cli) -> Result<()> {
     let config_path = cli
         .config
-        .as_ref()
-        .map(|x| Cow::from(x.as_path()))
+        .clone()
         .or_else(|| {
-            env::var_os("XDG_CONFIG_HOME")
-                .map(|x| Cow::from(PathBuf::from(x).join("lilight/lilight.toml")))
+            env::var_os("XDG_CONFIG_HOME").map(|x| PathBuf::from(x).join("lilight/lilight.toml"))
         })
         .or_else(|| {
-            env::var_os("HOME")
-                .map(|x| Cow::from(PathBuf::from(x).join(".config/lilight/lilight.toml")))
+            env::var_os("HOME").map(|x| PathBuf::from(x).join(".config/lilight/lilight.toml"))
         })
-        .unwrap_or(Cow::from(Path::new("~/.config/lilight/lilight.toml")));
-    let config = match fs::read(&config_path) {
-        Ok(x) => match toml::from_slice(&x) {
-            Ok(x) => x,
-            Err(e) => {
-                eprintln!("parser error while reading config file `{config_path:?}`: {e}");
-                Config::default()
-            }
-        },
+        .unwrap_or(PathBuf::from("~/.config/lilight/lilight.toml"));
+    // a missing or broken config file is only ignored when it wasn't asked for, and `calibrate`
+    // creates the file it is given
+    let creates_config = matches!(cli.command, Command::Calibrate { .. });
+    let config = match Config::read(&config_path) {
+        Ok(config) => config,
+        Err(Error::Io { source, .. })
+            if source.kind() == io::ErrorKind::NotFound
+                && (cli.config.is_none() || creates_config) =>
+        {
+            debug!("no config file at `{}`", config_path.display());
+            Config::default()
+        }
+        Err(e) if cli.config.is_some() => return Err(e),
         Err(e) => {
-            eprintln!("connot read the config file at `{config_path:?}`: {e}");
+            warn!("{e}, using the default config");
             Config::default()
         }
     };
+    logger::set_level(config.log_level, cli.verbose, cli.quiet);
 
-    let setting = Setting::new(cli, config);
+    let setting = Setting::new(cli.clone(), config);
 
-    dbg!(&setting);
+    trace!("{setting:#?}");
 
     match setting {
         Setting::Set {
             value,
             device,
-            transition_enable,
-            transition_time,
-            transition_step,
+            transition,
+            limits,
         } => {
-            let mut device = match device {
-                Some(name) => Device::new(Path::new("/sys/class").join(name))?,
-                None => {
-                    let (device, errors) = Device::first();
-                    if !errors.is_empty() {
-                        eprintln!("error while getting first device: {errors:#?}");
+            let mut device = open_device(device)?;
+            set_brightness(&mut device, value, &transition, &limits)
+        }
+        Setting::Get {
+            max,
+            actual,
+            format,
+            device,
+            all,
+        } => {
+            let print = |device: &mut Device| -> Result<()> {
+                let (label, value) = if max {
+                    ("max-brightness", device.get_max_brightness()?)
+                } else if actual {
+                    ("actual-brightness", device.get_actual_brightness()?)
+                } else {
+                    ("brightness", device.get_brightness()?)
+                };
+                match format {
+                    Some(format) => {
+                        println!("{}", format.format(value, device.get_max_brightness()?))
                     }
-                    device.ok_or(io::Error::from(io::ErrorKind::NotFound))?
+                    None => println!("device: {device:#?}, {label}: {value}"),
                 }
+                Ok(())
             };
-            set_brightness(
-                &mut device,
-                value,
-                transition_enable,
-                transition_time,
-                transition_step,
-            )
-        }
-        Setting::Get { max, device, all } => {
             if all {
                 let (devices, errors) = Device::all();
-                if !errors.is_empty() {
-                    eprintln!("error while getting all devices: {errors:#?}");
+                for e in errors {
+                    warn!("error while getting all devices: {e}");
                 }
                 for mut device in devices {
-                    if max {
-                        println!(
-                            "device: {device:#?}, max-brightness: {}",
-                            device.get_max_brightness()?
-                        );
-                    } else {
-                        println!(
-                            "device: {device:#?}, brightness: {}",
-                            device.get_brightness()?
-                        );
-                    }
+                    print(&mut device)?;
                 }
                 Ok(())
             } else {
-                let mut device = match device {
-                    Some(name) => Device::new(Path::new("/sys/class").join(name))?,
-                    None => {
-                        let (devices, errors) = Device::all();
-                        if !errors.is_empty() {
-                            eprintln!("error while getting all devices: {errors:#?}");
-                        }
-                        devices
-                            .into_iter()
-                            .next()
-                            .ok_or(io::Error::from(io::ErrorKind::NotFound))?
-                    }
-                };
-                if max {
-                    println!(
-                        "device: {device:#?}, max-brightness: {}",
-                        device.get_max_brightness()?
-                    );
-                } else {
-                    println!(
-                        "device: {device:#?}, brightness: {}",
-                        device.get_brightness()?
-                    );
-                }
-                Ok(())
+                print(&mut open_device(device)?)
             }
         }
+        Setting::Power { state, device } => {
+            let mut device = open_device(device)?;
+            let on = match state {
+                PowerState::On => true,
+                PowerState::Off => false,
+                // a device without `bl_power` is always on, `set_power` explains why
+                PowerState::Toggle => device.get_power()? == Some(false),
+            };
+            device.set_power(on)
+        }
         Setting::List => {
             let (devices, errors) = Device::all();
-            if !errors.is_empty() {
-                eprintln!("error while getting all devices: {errors:#?}");
+            for e in errors {
+                warn!("error while getting all devices: {e}");
             }
             for device in devices {
-                println!("{:?}", device.name);
+                println!("{:?}", device.name());
+            }
+            Ok(())
+        }
+        Setting::SensorList => {
+            let (iios, errors) = Iio::all();
+            for e in errors {
+                warn!("error while getting all iio: {e}");
+            }
+            for iio in iios {
+                let channels = match iio.channels() {
+                    Ok(channels) => channels.join(", "),
+                    Err(e) => format!("error: {e}"),
+                };
+                println!(
+                    "{}: channels: {channels}, scale: {}, offset: {}",
+                    iio.describe(),
+                    iio.scale(),
+                    iio.offset(),
+                );
             }
             Ok(())
         }
-        Setting::Daemon {
+        Setting::SensorRead {
+            sensors,
+            fusion,
+            weights,
+            follow,
+        } => {
+            let mut iios = if sensors.is_empty() {
+                vec![open_iio(None)?]
+            } else {
+                sensors
+                    .into_iter()
+                    .map(|x| open_iio(Some(x)))
+                    .collect::<Result<Vec<_>>>()?
+            };
+            loop {
+                let readings = iios
+                    .iter_mut()
+                    .map(|x| x.get_illuminance().map(Some))
+                    .collect::<Result<Vec<_>>>()?;
+                match fusion.combine(&readings, &weights) {
+                    Some(illuminance) => println!("{illuminance}"),
+                    None => warn!("the sensors' weights add up to 0"),
+                }
+                if !follow {
+                    return Ok(());
+                }
+                thread::sleep(Duration::from_millis(1000));
+            }
+        }
+        Setting::Calibrate {
             device,
-            transition_enable,
-            transition_time,
-            transition_step,
             iio_sensor,
-            curve,
+            unit,
+            device_curve,
         } => {
-            let mut device = match device {
-                Some(name) => Device::new(Path::new("/sys/class").join(name))?,
+            let curve_owner = device.clone().filter(|_| device_curve);
+            let mut device = open_device(device)?;
+            let mut iio = open_iio(iio_sensor)?;
+            calibrate::run(
+                &mut device,
+                &mut iio,
+                unit,
+                &config_path,
+                curve_owner.as_deref(),
+            )
+        }
+        Setting::SystemdUnit { socket } => {
+            let exe = env::current_exe().unwrap_or(PathBuf::from("lilight"));
+            print!("{}", systemd::unit(&exe, socket));
+            Ok(())
+        }
+        Setting::UdevRule { group, destination } => {
+            let rule = udev::rule(&group);
+            match destination {
+                Some(destination) => udev::install(&rule, &destination),
                 None => {
-                    let (devices, errors) = Device::all();
-                    if !errors.is_empty() {
-                        eprintln!("error while getting all devices: {errors:#?}");
-                    }
-                    devices
-                        .into_iter()
-                        .next()
-                        .ok_or(io::Error::from(io::ErrorKind::NotFound))?
+                    print!("{rule}");
+                    Ok(())
                 }
-            };
-            let mut iio = match iio_sensor {
-                Some(x) => Iio::new(Path::new("/sys/bus/iio/devices/").join(x))?,
-                None => {
-                    let (iio, errors) = Iio::first();
-                    if !errors.is_empty() {
-                        eprintln!("error while getting first iio: {errors:#?}");
+            }
+        }
+        Setting::Doctor { json } => {
+            doctor::run(&config_path, json);
+            Ok(())
+        }
+        Setting::Daemon(setting) => {
+            daemon::run(setting, &config_path, || match Config::read(&config_path) {
+                Ok(config) => {
+                    logger::set_level(config.log_level, cli.verbose, cli.quiet);
+                    match Setting::new(cli.clone(), config) {
+                        Setting::Daemon(setting) => Some(setting),
+                        _ => unreachable!(),
                     }
-                    iio.ok_or(io::Error::from(io::ErrorKind::NotFound))?
                 }
-            };
-            loop {
-                match iio.get_illuminance() {
-                    Ok(illuminance) => {
-                        let new_brightness = curve.apply(illuminance);
-                        println!("illuminance: {illuminance}, y: {new_brightness}",);
-                        if let Err(e) = set_brightness(
-                            &mut device,
-                            Value {
-                                prefix: Prefix::None,
-                                r#type: Type::Percentage,
-                                num: new_brightness,
-                            },
-                            transition_enable,
-                            transition_time,
-                            transition_step,
-                        ) {
-                            eprintln!("error while setting brightness: {e}");
-                        }
-                    }
-                    Err(e) => eprintln!("error: {e}"),
+                Err(e) => {
+                    error!("{e}, keeping the current config");
+                    None
                 }
-                thread::sleep(Duration::from_millis(1000)); // TODO: let user control this
-            }
+            })
         }
     }
 }
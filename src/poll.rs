@@ -0,0 +1,25 @@
+use std::{
+    io,
+    os::fd::{AsRawFd, BorrowedFd},
+    time::Duration,
+};
+
+/// Wait until one of `fds` is readable or `timeout` has passed.
+pub fn poll(fds: &[BorrowedFd], timeout: Duration) -> io::Result<()> {
+    let mut pollfds = fds
+        .iter()
+        .map(|fd| libc::pollfd {
+            fd: fd.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        })
+        .collect::<Vec<_>>();
+    let timeout = timeout.as_millis().min(i32::MAX as u128) as i32;
+    if unsafe { libc::poll(pollfds.as_mut_ptr(), pollfds.len() as libc::nfds_t, timeout) } < 0 {
+        let e = io::Error::last_os_error();
+        if e.kind() != io::ErrorKind::Interrupted {
+            return Err(e);
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,72 @@
+use std::{
+    io, mem,
+    os::fd::{AsFd, AsRawFd, BorrowedFd, FromRawFd, OwnedFd},
+};
+
+/// Delivers `signals` through a file descriptor instead of asynchronous handlers, so they can be
+/// handled from the daemon's loop. The thread's previous signal mask is restored on drop.
+pub struct Signals {
+    fd: OwnedFd,
+    old_mask: libc::sigset_t,
+}
+
+impl Signals {
+    pub fn new(signals: &[libc::c_int]) -> io::Result<Self> {
+        let mut old_mask = unsafe { mem::zeroed() };
+        let fd = unsafe {
+            let mut set = mem::zeroed();
+            libc::sigemptyset(&mut set);
+            for &signal in signals {
+                libc::sigaddset(&mut set, signal);
+            }
+            let e = libc::pthread_sigmask(libc::SIG_BLOCK, &set, &mut old_mask);
+            if e != 0 {
+                return Err(io::Error::from_raw_os_error(e));
+            }
+            libc::signalfd(-1, &set, libc::SFD_NONBLOCK | libc::SFD_CLOEXEC)
+        };
+        if fd < 0 {
+            let e = io::Error::last_os_error();
+            unsafe { libc::pthread_sigmask(libc::SIG_SETMASK, &old_mask, std::ptr::null_mut()) };
+            return Err(e);
+        }
+        Ok(Self {
+            fd: unsafe { OwnedFd::from_raw_fd(fd) },
+            old_mask,
+        })
+    }
+    /// Signals received since the last call, without blocking.
+    pub fn read(&self) -> io::Result<Vec<libc::c_int>> {
+        let mut signals = vec![];
+        loop {
+            let mut info: libc::signalfd_siginfo = unsafe { mem::zeroed() };
+            let n = unsafe {
+                libc::read(
+                    self.fd.as_raw_fd(),
+                    &mut info as *mut libc::signalfd_siginfo as *mut libc::c_void,
+                    mem::size_of::<libc::signalfd_siginfo>(),
+                )
+            };
+            if n < 0 {
+                let e = io::Error::last_os_error();
+                return match e.kind() {
+                    io::ErrorKind::WouldBlock => Ok(signals),
+                    _ => Err(e),
+                };
+            }
+            signals.push(info.ssi_signo as libc::c_int);
+        }
+    }
+}
+
+impl AsFd for Signals {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.fd.as_fd()
+    }
+}
+
+impl Drop for Signals {
+    fn drop(&mut self) {
+        unsafe { libc::pthread_sigmask(libc::SIG_SETMASK, &self.old_mask, std::ptr::null_mut()) };
+    }
+}
@@ -0,0 +1,146 @@
+//! Integration with systemd: readiness notification, socket activation and unit files.
+
+use std::{
+    env, io,
+    os::{
+        fd::{FromRawFd, OwnedFd},
+        linux::net::SocketAddrExt,
+        unix::net::{SocketAddr, UnixDatagram},
+    },
+    path::Path,
+    process,
+    time::Duration,
+};
+
+/// First file descriptor passed by socket activation.
+const LISTEN_FDS_START: i32 = 3;
+
+/// Sends `sd_notify(3)` messages to the service manager.
+pub struct Notifier {
+    socket: UnixDatagram,
+    addr: SocketAddr,
+}
+
+impl Notifier {
+    /// Connect to `$NOTIFY_SOCKET`, if the service manager set one.
+    pub fn from_env() -> Option<io::Result<Self>> {
+        let path = env::var_os("NOTIFY_SOCKET")?;
+        let addr = match path.to_str().and_then(|x| x.strip_prefix('@')) {
+            Some(name) => SocketAddr::from_abstract_name(name),
+            None => SocketAddr::from_pathname(Path::new(&path)),
+        };
+        Some(addr.and_then(Self::new))
+    }
+    /// Send to `addr` instead of `$NOTIFY_SOCKET`.
+    pub fn new(addr: SocketAddr) -> io::Result<Self> {
+        Ok(Self {
+            socket: UnixDatagram::unbound()?,
+            addr,
+        })
+    }
+    /// Send newline separated `KEY=VALUE` assignments, e.g. `READY=1`.
+    pub fn notify(&self, state: &str) -> io::Result<()> {
+        self.socket.send_to_addr(state.as_bytes(), &self.addr)?;
+        Ok(())
+    }
+}
+
+/// How often the service manager expects `WATCHDOG=1`, from `$WATCHDOG_USEC`.
+pub fn watchdog_interval() -> Option<Duration> {
+    if let Some(pid) = env::var_os("WATCHDOG_PID")
+        && pid.to_str() != Some(&process::id().to_string())
+    {
+        return None;
+    }
+    let usec = env::var("WATCHDOG_USEC").ok()?.parse().ok()?;
+    Some(Duration::from_micros(usec))
+}
+
+/// Sockets passed by socket activation (`$LISTEN_PID` and `$LISTEN_FDS`).
+pub fn listen_fds() -> Vec<OwnedFd> {
+    let count = listen_fds_count(
+        env::var("LISTEN_PID").ok().as_deref(),
+        env::var("LISTEN_FDS").ok().as_deref(),
+        process::id(),
+    );
+    (0..count)
+        .map(|i| unsafe { OwnedFd::from_raw_fd(LISTEN_FDS_START + i) })
+        .collect()
+}
+
+fn listen_fds_count(listen_pid: Option<&str>, listen_fds: Option<&str>, pid: u32) -> i32 {
+    if listen_pid.and_then(|x| x.parse().ok()) != Some(pid) {
+        return 0;
+    }
+    listen_fds.and_then(|x| x.parse().ok()).unwrap_or(0)
+}
+
+/// A user service running `exe daemon`, or with `socket` the socket unit activating it.
+pub fn unit(exe: &Path, socket: bool) -> String {
+    if socket {
+        return "\
+[Unit]
+Description=lilight control socket
+
+[Socket]
+ListenStream=%t/lilight.sock
+
+[Install]
+WantedBy=sockets.target
+"
+        .to_owned();
+    }
+    format!(
+        "\
+[Unit]
+Description=lilight ambient light brightness daemon
+PartOf=graphical-session.target
+After=graphical-session.target
+
+[Service]
+Type=notify
+ExecStart={} daemon
+ExecReload=kill -HUP $MAINPID
+WatchdogSec=30
+Restart=on-failure
+
+[Install]
+WantedBy=graphical-session.target
+",
+        exe.display()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        env, fs,
+        os::unix::net::{SocketAddr, UnixDatagram},
+        process,
+    };
+
+    use super::{Notifier, listen_fds_count};
+
+    #[test]
+    fn notify() {
+        let path = env::temp_dir().join(format!("lilight-notify-{}.sock", process::id()));
+        let _ = fs::remove_file(&path);
+        let service_manager = UnixDatagram::bind(&path).unwrap();
+
+        let notifier = Notifier::new(SocketAddr::from_pathname(&path).unwrap()).unwrap();
+        notifier.notify("READY=1\nSTATUS=ok").unwrap();
+
+        let mut buf = [0; 64];
+        let n = service_manager.recv(&mut buf).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(&buf[..n], b"READY=1\nSTATUS=ok");
+    }
+
+    #[test]
+    fn listen_fds() {
+        assert_eq!(listen_fds_count(Some("42"), Some("1"), 42), 1);
+        assert_eq!(listen_fds_count(Some("41"), Some("1"), 42), 0);
+        assert_eq!(listen_fds_count(None, Some("1"), 42), 0);
+        assert_eq!(listen_fds_count(Some("42"), None, 42), 0);
+    }
+}
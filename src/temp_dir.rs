@@ -0,0 +1,41 @@
+//! Scratch directories for tests.
+
+use std::{
+    env, fs,
+    ops::Deref,
+    path::{Path, PathBuf},
+    process,
+};
+
+/// An empty directory in the temporary directory, removed with its contents on drop so a failed
+/// test doesn't leave it behind.
+pub(crate) struct TempDir(PathBuf);
+
+impl TempDir {
+    /// `lilight-<name>-<pid>`, emptied if a previous run left it behind.
+    pub(crate) fn new(name: &str) -> Self {
+        let path = env::temp_dir().join(format!("lilight-{name}-{}", process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        Self(path)
+    }
+}
+
+impl Deref for TempDir {
+    type Target = Path;
+    fn deref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for TempDir {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
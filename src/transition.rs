@@ -0,0 +1,137 @@
+//! Fading between two brightness values.
+
+use std::time::Duration;
+
+/// The brightness values written while fading from `from` to `to`, each paired with the delay
+/// before the next write. The last value is always `to`, and nothing is written when `from` is
+/// already `to`.
+///
+/// Consecutive values are always different: when there are fewer raw values between `from` and
+/// `to` than writes in `time`, each value is written once and the writes are spread over `time`.
+#[derive(Debug)]
+pub struct Transition {
+    from: i32,
+    to: i32,
+    steps: u64,
+    time: u64,
+    step: u64,
+    i: u64,
+}
+
+/// How brightness changes fade.
+#[derive(Clone, Copy, Debug)]
+#[non_exhaustive]
+pub struct TransitionSetting {
+    /// Fade instead of jumping to the new brightness.
+    pub enable: bool,
+    /// Milliseconds a fade takes.
+    pub time: u64,
+    /// Milliseconds between two writes during a fade.
+    pub step: u64,
+}
+
+impl TransitionSetting {
+    /// Fade over `time` milliseconds, writing every `step` milliseconds.
+    pub fn new(time: u64, step: u64) -> Self {
+        Self {
+            enable: true,
+            time,
+            step,
+        }
+    }
+    /// Jump to the new brightness in a single write.
+    pub fn disabled() -> Self {
+        Self {
+            enable: false,
+            ..Self::default()
+        }
+    }
+}
+
+impl Default for TransitionSetting {
+    /// A 100 ms fade at about 60 writes per second, like the config file's default.
+    fn default() -> Self {
+        Self::new(100, 17)
+    }
+}
+
+impl Transition {
+    /// Fade from `from` to `to` as `setting` says.
+    pub fn new(from: i32, to: i32, setting: &TransitionSetting) -> Self {
+        let TransitionSetting { enable, time, step } = *setting;
+        let steps = if enable && time != 0 && step != 0 {
+            time.div_ceil(step)
+        } else {
+            1
+        };
+        let steps = steps.min(from.abs_diff(to) as u64);
+        Self {
+            from,
+            to,
+            steps,
+            time,
+            // spread the writes over `time` when there are fewer values than steps
+            step: step.max(time.checked_div(steps).unwrap_or(0)),
+            i: 1,
+        }
+    }
+}
+
+impl Iterator for Transition {
+    type Item = (i32, Duration);
+    fn next(&mut self) -> Option<Self::Item> {
+        let i = self.i;
+        if i > self.steps {
+            return None;
+        }
+        self.i += 1;
+        if i == self.steps {
+            return Some((self.to, Duration::ZERO));
+        }
+        let value = self.from + (self.to - self.from) * i as i32 / self.steps as i32;
+        Some((
+            value,
+            Duration::from_millis(self.step.min(self.time.saturating_sub(self.step * i))),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{Transition, TransitionSetting};
+
+    #[test]
+    fn steps() {
+        let steps = Transition::new(0, 60, &TransitionSetting::new(100, 17)).collect::<Vec<_>>();
+        assert_eq!(steps.len(), 6);
+        assert_eq!(steps.last(), Some(&(60, Duration::ZERO)));
+        assert_eq!(steps[0], (10, Duration::from_millis(17)));
+        let total = steps.iter().map(|(_, x)| *x).sum::<Duration>();
+        assert_eq!(total, Duration::from_millis(83));
+    }
+
+    #[test]
+    fn disabled() {
+        let steps = Transition::new(0, 60, &TransitionSetting::disabled()).collect::<Vec<_>>();
+        assert_eq!(steps, vec![(60, Duration::ZERO)]);
+        assert_eq!(
+            Transition::new(60, 60, &TransitionSetting::default()).count(),
+            0
+        );
+    }
+
+    #[test]
+    fn coalesced() {
+        let steps = Transition::new(10, 7, &TransitionSetting::default()).collect::<Vec<_>>();
+        assert_eq!(
+            steps,
+            vec![
+                (9, Duration::from_millis(33)),
+                (8, Duration::from_millis(33)),
+                (7, Duration::ZERO)
+            ]
+        );
+    }
+}
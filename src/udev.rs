@@ -0,0 +1,72 @@
+//! udev rules letting users without root change the brightness.
+
+use std::{fs, path::Path, process::Command};
+
+use log::{info, warn};
+
+use crate::error::{Error, Result};
+
+/// Where `lilight udev-rule --install` writes the rule by default.
+pub const DEFAULT_DESTINATION: &str = "/etc/udev/rules.d/90-lilight-backlight.rules";
+
+/// A udev rule giving `group` write access to `brightness` of every backlight and led, and to
+/// `bl_power` of backlights.
+pub fn rule(group: &str) -> String {
+    let mut rule =
+        format!("# Generated by lilight: lets the `{group}` group change the brightness\n");
+    for (subsystem, attributes) in [
+        ("backlight", &["brightness", "bl_power"][..]),
+        ("leds", &["brightness"]),
+    ] {
+        rule += &format!("ACTION==\"add\", SUBSYSTEM==\"{subsystem}\"");
+        for attribute in attributes {
+            rule += &format!(
+                ", RUN+=\"/bin/chgrp {group} /sys/class/{subsystem}/%k/{attribute}\", \
+                 RUN+=\"/bin/chmod g+w /sys/class/{subsystem}/%k/{attribute}\""
+            );
+        }
+        rule += "\n";
+    }
+    rule
+}
+
+/// Write `rule` to `destination`, then ask udev to reload its rules and apply them to the
+/// devices already present.
+pub fn install(rule: &str, destination: &Path) -> Result<()> {
+    if let Some(parent) = destination.parent() {
+        fs::create_dir_all(parent).map_err(|e| Error::io(parent, e))?;
+    }
+    fs::write(destination, rule).map_err(|e| Error::io(destination, e))?;
+    info!("wrote the udev rule to `{}`", destination.display());
+    for args in [
+        &["control", "--reload"][..],
+        &[
+            "trigger",
+            "--action=add",
+            "--subsystem-match=backlight",
+            "--subsystem-match=leds",
+        ],
+    ] {
+        match Command::new("udevadm").args(args).status() {
+            Ok(status) if status.success() => (),
+            Ok(status) => warn!("`udevadm {}` failed with {status}", args.join(" ")),
+            Err(e) => warn!("cannot run `udevadm {}`: {e}", args.join(" ")),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::rule;
+
+    #[test]
+    fn group() {
+        let rule = rule("wheel");
+        assert_eq!(rule.lines().count(), 3);
+        assert!(rule.contains(
+            "SUBSYSTEM==\"leds\", RUN+=\"/bin/chgrp wheel /sys/class/leds/%k/brightness\""
+        ));
+        assert!(rule.contains("RUN+=\"/bin/chmod g+w /sys/class/backlight/%k/bl_power\"\n"));
+    }
+}
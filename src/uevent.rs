@@ -0,0 +1,127 @@
+use std::{
+    io, mem,
+    os::fd::{AsFd, AsRawFd, BorrowedFd, FromRawFd, OwnedFd, RawFd},
+};
+
+/// A kernel uevent, e.g. `add@/devices/.../backlight/amdgpu_bl1`.
+#[derive(Debug)]
+pub struct Uevent {
+    pub action: String,
+    pub subsystem: String,
+}
+
+impl Uevent {
+    /// Stands for the uevents the kernel dropped because the socket's buffer was full, any of
+    /// which may have been a hotplug.
+    fn overflow() -> Self {
+        Self {
+            action: "overflow".to_owned(),
+            subsystem: String::new(),
+        }
+    }
+    /// Whether this is [`Uevent::overflow`].
+    pub fn is_overflow(&self) -> bool {
+        self.action == "overflow" && self.subsystem.is_empty()
+    }
+    fn parse(buf: &[u8]) -> Option<Self> {
+        let mut fields = buf.split(|x| *x == 0).map(String::from_utf8_lossy);
+        let header = fields.next()?;
+        let action = header.split_once('@')?.0.to_owned();
+        let subsystem = fields
+            .find_map(|x| x.strip_prefix("SUBSYSTEM=").map(str::to_owned))
+            .unwrap_or_default();
+        Some(Self { action, subsystem })
+    }
+}
+
+/// Netlink socket receiving kernel uevents.
+pub struct UeventSocket(OwnedFd);
+
+impl UeventSocket {
+    pub fn new() -> io::Result<Self> {
+        let fd = unsafe {
+            libc::socket(
+                libc::AF_NETLINK,
+                libc::SOCK_DGRAM | libc::SOCK_CLOEXEC | libc::SOCK_NONBLOCK,
+                libc::NETLINK_KOBJECT_UEVENT,
+            )
+        };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let socket = Self(unsafe { OwnedFd::from_raw_fd(fd) });
+        let mut addr: libc::sockaddr_nl = unsafe { mem::zeroed() };
+        addr.nl_family = libc::AF_NETLINK as libc::sa_family_t;
+        // the kernel's multicast group, as opposed to the one udev rebroadcasts on
+        addr.nl_groups = 1;
+        if unsafe {
+            libc::bind(
+                fd,
+                &addr as *const libc::sockaddr_nl as *const libc::sockaddr,
+                mem::size_of::<libc::sockaddr_nl>() as libc::socklen_t,
+            )
+        } != 0
+        {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(socket)
+    }
+    /// Uevents received since the last call, without blocking. Uevents dropped by the kernel
+    /// (`ENOBUFS`, e.g. during a burst on resume) are reported as one [`Uevent::is_overflow`].
+    pub fn recv(&self) -> io::Result<Vec<Uevent>> {
+        let mut uevents = vec![];
+        let mut buf = [0; 8192];
+        loop {
+            let n = unsafe {
+                libc::recv(
+                    self.0.as_raw_fd(),
+                    buf.as_mut_ptr() as *mut libc::c_void,
+                    buf.len(),
+                    0,
+                )
+            };
+            if n < 0 {
+                let e = io::Error::last_os_error();
+                match e.kind() {
+                    io::ErrorKind::WouldBlock => return Ok(uevents),
+                    _ if e.raw_os_error() == Some(libc::ENOBUFS) => {
+                        uevents.push(Uevent::overflow());
+                        continue;
+                    }
+                    _ => return Err(e),
+                }
+            }
+            uevents.extend(Uevent::parse(&buf[..n as usize]));
+        }
+    }
+}
+
+impl AsFd for UeventSocket {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.0.as_fd()
+    }
+}
+
+impl AsRawFd for UeventSocket {
+    fn as_raw_fd(&self) -> RawFd {
+        self.0.as_raw_fd()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Uevent;
+
+    #[test]
+    fn parse() {
+        let uevent = Uevent::parse(
+            b"add@/devices/platform/x/iio:device1\0ACTION=add\0DEVPATH=/devices/platform/x/iio:device1\0SUBSYSTEM=iio\0SEQNUM=1\0",
+        )
+        .unwrap();
+        assert_eq!(uevent.action, "add");
+        assert_eq!(uevent.subsystem, "iio");
+        assert!(Uevent::parse(b"libudev\0").is_none());
+        assert!(!uevent.is_overflow());
+        assert!(Uevent::overflow().is_overflow());
+    }
+}
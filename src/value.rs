@@ -0,0 +1,264 @@
+use std::{error, fmt, str::FromStr};
+
+/// A brightness as given on the command line, e.g. `50%`, `+2.5%`, `*1.5`, `=10` or `max`.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct Value {
+    /// Whether the value is absolute or relative.
+    pub prefix: Prefix,
+    /// Whether `num` is raw or a percentage.
+    pub r#type: Type,
+    /// The magnitude, the sign being in `prefix`.
+    pub num: f64,
+}
+
+impl Value {
+    /// An absolute raw brightness.
+    pub fn number(num: i32) -> Self {
+        Self {
+            prefix: Prefix::None,
+            r#type: Type::Number,
+            num: num as f64,
+        }
+    }
+    /// An absolute percentage of `max_brightness`.
+    pub fn percentage(num: f64) -> Self {
+        Self {
+            prefix: Prefix::None,
+            r#type: Type::Percentage,
+            num,
+        }
+    }
+    /// `num` added to the current brightness, like `+10%`.
+    pub fn plus(r#type: Type, num: f64) -> Self {
+        Self {
+            prefix: Prefix::Plus,
+            r#type,
+            num,
+        }
+    }
+    /// `num` subtracted from the current brightness, like `-10%`.
+    pub fn minus(r#type: Type, num: f64) -> Self {
+        Self {
+            prefix: Prefix::Minus,
+            r#type,
+            num,
+        }
+    }
+    /// The current brightness multiplied by `factor`, like `*1.5`.
+    pub fn multiply(factor: f64) -> Self {
+        Self {
+            prefix: Prefix::Multiply,
+            r#type: Type::Number,
+            num: factor,
+        }
+    }
+    /// The current brightness divided by `divisor`, like `/2`.
+    ///
+    /// # Panics
+    ///
+    /// If `divisor` is 0.
+    pub fn divide(divisor: f64) -> Self {
+        assert!(divisor != 0.0, "cannot divide by 0");
+        Self {
+            prefix: Prefix::Divide,
+            r#type: Type::Number,
+            num: divisor,
+        }
+    }
+}
+
+/// Why a [`Value`] couldn't be parsed, pointing at the offending character.
+#[derive(Debug)]
+pub struct ParseValueError {
+    input: String,
+    position: usize,
+    reason: String,
+}
+
+impl ParseValueError {
+    fn new(input: &str, position: usize, reason: impl Into<String>) -> Self {
+        Self {
+            input: input.to_owned(),
+            position,
+            reason: reason.into(),
+        }
+    }
+}
+
+impl fmt::Display for ParseValueError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.input.is_empty() {
+            return write!(f, "{}", self.reason);
+        }
+        let column = self.input[..self.position].chars().count();
+        write!(
+            f,
+            "{} at character {}\n  {}\n  {}^",
+            self.reason,
+            column + 1,
+            self.input,
+            " ".repeat(column)
+        )
+    }
+}
+
+impl error::Error for ParseValueError {}
+
+impl FromStr for Value {
+    type Err = ParseValueError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = |position, reason: &str| ParseValueError::new(s, position, reason);
+        match s {
+            "" => return Err(error(0, "the value is empty")),
+            "max" => return Ok(Self::percentage(100.0)),
+            "half" => return Ok(Self::percentage(50.0)),
+            // the lowest brightness that isn't off, raised to the device's `min` if it has one
+            "min" => return Ok(Self::number(1)),
+            "off" => return Ok(Self::number(0)),
+            _ if s.chars().all(char::is_alphabetic) => {
+                return Err(error(
+                    0,
+                    "unknown keyword, expected `max`, `min`, `half` or `off`",
+                ));
+            }
+            _ => {}
+        }
+        let (prefix, start) = match s.as_bytes()[0] {
+            b'+' => (Prefix::Plus, 1),
+            b'-' => (Prefix::Minus, 1),
+            b'*' => (Prefix::Multiply, 1),
+            b'/' => (Prefix::Divide, 1),
+            b'=' => (Prefix::None, 1),
+            _ => (Prefix::None, 0),
+        };
+        let (r#type, end) = match s.strip_suffix('%') {
+            Some(rest) => (Type::Percentage, rest.len()),
+            None => (Type::Number, s.len()),
+        };
+        let digits = &s[start..end];
+        if digits.is_empty() {
+            return Err(error(start, "expected a number"));
+        }
+        let mut dot = false;
+        for (i, c) in digits.char_indices() {
+            match c {
+                '0'..='9' => {}
+                '.' if !dot => dot = true,
+                '.' => return Err(error(start + i, "a number has at most one `.`")),
+                '%' => return Err(error(start + i, "`%` can only end the value")),
+                c => return Err(error(start + i, &format!("unexpected `{c}`"))),
+            }
+        }
+        if digits == "." {
+            return Err(error(start, "expected a number"));
+        }
+        let num = digits.parse::<f64>().expect("checked above");
+        if matches!(prefix, Prefix::Multiply | Prefix::Divide) {
+            if let Type::Percentage = r#type {
+                return Err(error(end, "a factor can't be a percentage"));
+            }
+            if matches!(prefix, Prefix::Divide) && num == 0.0 {
+                return Err(error(start, "cannot divide by 0"));
+            }
+        }
+        Ok(Self {
+            prefix,
+            r#type,
+            num,
+        })
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let prefix = match self.prefix {
+            Prefix::None => "",
+            Prefix::Plus => "+",
+            Prefix::Minus => "-",
+            Prefix::Multiply => "*",
+            Prefix::Divide => "/",
+        };
+        let suffix = match self.r#type {
+            Type::Number => "",
+            Type::Percentage => "%",
+        };
+        write!(f, "{prefix}{}{suffix}", self.num)
+    }
+}
+
+/// Whether a [`Value`] is absolute or relative to the current brightness.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub enum Prefix {
+    /// Absolute, with no prefix or `=`.
+    None,
+    /// Added to the current brightness.
+    Plus,
+    /// Subtracted from the current brightness.
+    Minus,
+    /// Multiplies the current brightness.
+    Multiply,
+    /// Divides the current brightness.
+    Divide,
+}
+
+/// The unit of a [`Value`].
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub enum Type {
+    /// Raw brightness, up to `max_brightness`.
+    Number,
+    /// Percent of `max_brightness`.
+    Percentage,
+}
+
+/// Raw brightness that is `percentage` percent of `max`, rounded to the nearest integer.
+pub fn to_value(max: i32, percentage: f64) -> i32 {
+    (percentage * max as f64 / 100.0).round() as i32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Prefix, Type, Value};
+
+    #[test]
+    fn parse() {
+        let value = "+2.5%".parse::<Value>().unwrap();
+        assert!(matches!(value.prefix, Prefix::Plus));
+        assert!(matches!(value.r#type, Type::Percentage));
+        assert_eq!(value.num, 2.5);
+        let value = "/2".parse::<Value>().unwrap();
+        assert!(matches!(value.prefix, Prefix::Divide));
+        assert_eq!(value.num, 2.0);
+        let value = "=10".parse::<Value>().unwrap();
+        assert!(matches!(value.prefix, Prefix::None));
+        assert!(matches!(value.r#type, Type::Number));
+        let value = "half".parse::<Value>().unwrap();
+        assert!(matches!(value.r#type, Type::Percentage));
+        assert_eq!(value.num, 50.0);
+    }
+
+    #[test]
+    fn errors() {
+        let e = "+2,5%".parse::<Value>().unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "unexpected `,` at character 3\n  +2,5%\n    ^"
+        );
+        assert!("*1.5%".parse::<Value>().is_err());
+        assert!("/0".parse::<Value>().is_err());
+        assert!("1.2.3".parse::<Value>().is_err());
+        assert!("%".parse::<Value>().is_err());
+        assert!("maximum".parse::<Value>().is_err());
+    }
+
+    #[test]
+    fn constructors() {
+        assert_eq!(Value::plus(Type::Percentage, 10.0).to_string(), "+10%");
+        assert_eq!(Value::minus(Type::Number, 2.0).to_string(), "-2");
+        assert_eq!(Value::multiply(1.5).to_string(), "*1.5");
+        assert_eq!(Value::divide(2.0).to_string(), "/2");
+        assert_eq!(Value::percentage(50.0).to_string(), "50%");
+    }
+}